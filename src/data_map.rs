@@ -0,0 +1,75 @@
+// Copyright 2018 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under The General Public License (GPL), version 3.
+// Unless required by applicable law or agreed to in writing, the SAFE Network Software distributed
+// under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied. Please review the Licences for the specific language governing
+// permissions and limitations relating to use of the SAFE Network Software.
+
+use sequential::EncryptionScheme;
+
+/// Holds the name (post-encryption hash), plaintext hash and source size of a single chunk.
+#[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
+pub struct ChunkDetails {
+    /// Index of this chunk within the file.
+    pub chunk_num: u32,
+    /// The chunk's name in `Storage` - the hash of its ciphertext.
+    pub hash: Vec<u8>,
+    /// The hash of the chunk's plaintext, used to derive neighbouring chunks' keys.
+    pub pre_hash: Vec<u8>,
+    /// The number of plaintext bytes this chunk represents.
+    pub source_size: u64,
+}
+
+/// The result of self-encrypting some data: either the list of chunks, or - for data too small to
+/// split into three chunks - the content inline.
+///
+/// The chunked variant records the `chunk_size` and `scheme` that produced it so that
+/// `SelfEncryptor::new` can reconstruct the correct chunk boundaries and decryption path on read,
+/// without the caller having to remember which `ChunkConfig` was used to write the data.
+#[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
+pub enum DataMap {
+    /// The file was split into the given chunks under the recorded chunk size and scheme.
+    Chunks {
+        /// The chunks making up the file, in order.
+        chunks: Vec<ChunkDetails>,
+        /// The chunk size the data was split on.
+        chunk_size: u64,
+        /// The per-chunk encryption scheme used.
+        scheme: EncryptionScheme,
+    },
+    /// The file was small enough to hold inline.
+    Content(Vec<u8>),
+    /// There is no data.
+    None,
+}
+
+impl DataMap {
+    /// The total number of plaintext bytes described by this map.
+    pub fn len(&self) -> u64 {
+        match *self {
+            DataMap::Chunks { ref chunks, .. } => {
+                chunks.iter().map(|chunk| chunk.source_size).sum()
+            }
+            DataMap::Content(ref content) => content.len() as u64,
+            DataMap::None => 0,
+        }
+    }
+
+    /// Returns `true` if there is no data.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// The chunks, sorted by index, or an empty slice for the non-chunked variants.
+    pub fn get_sorted_chunks(&self) -> Vec<ChunkDetails> {
+        match *self {
+            DataMap::Chunks { ref chunks, .. } => {
+                let mut sorted = chunks.clone();
+                sorted.sort_by_key(|chunk| chunk.chunk_num);
+                sorted
+            }
+            _ => vec![],
+        }
+    }
+}
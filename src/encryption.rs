@@ -0,0 +1,124 @@
+// Copyright 2018 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under The General Public License (GPL), version 3.
+// Unless required by applicable law or agreed to in writing, the SAFE Network Software distributed
+// under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied. Please review the Licences for the specific language governing
+// permissions and limitations relating to use of the SAFE Network Software.
+
+//! Per-chunk encode/decode primitives shared by the encryptors and `SelfEncryptor`.
+//!
+//! A chunk's key material is the concatenation of its own plaintext hash and the plaintext hashes
+//! of its (up to) two predecessors, so changing chunk `i` only invalidates the ciphertext of
+//! chunks `i`, `i + 1` and `i + 2` - the locality relied on by `SelfEncryptor::write_at`.
+
+use ring::digest::{digest, SHA256};
+use sequential::EncryptionScheme;
+use SelfEncryptionError;
+
+// SHA-256 output length, in bytes.
+const HASH_LEN: usize = 32;
+
+// The plaintext hash of a chunk, used both as its `pre_hash` in the `DataMap` and as key material
+// for its neighbours.
+pub fn pre_hash(plaintext: &[u8]) -> Vec<u8> {
+    digest(&SHA256, plaintext).as_ref().to_vec()
+}
+
+// The post-encryption hash of a chunk - its name in `Storage`.
+pub fn post_hash(ciphertext: &[u8]) -> Vec<u8> {
+    digest(&SHA256, ciphertext).as_ref().to_vec()
+}
+
+// Fills in missing predecessors: a missing `pred1` becomes this chunk's own hash and a missing
+// `pred2` becomes the resolved `pred1`, so the first two chunks still have well-defined key
+// material without wrapping around to the end of the file.
+fn resolve_preds<'a>(pre: &'a [u8], pred1: &'a [u8], pred2: &'a [u8]) -> (&'a [u8], &'a [u8]) {
+    let pred1 = if pred1.is_empty() { pre } else { pred1 };
+    let pred2 = if pred2.is_empty() { pred1 } else { pred2 };
+    (pred1, pred2)
+}
+
+// Builds the 96-byte key material for a chunk from its own and its predecessors' plaintext hashes.
+// Missing predecessors (the first two chunks) have already been filled in by `resolve_preds`,
+// keeping the derivation local - no wrap-around - so truncation and edits stay cheap.
+fn key_material(pre: &[u8], pred1: &[u8], pred2: &[u8]) -> Vec<u8> {
+    let mut material = Vec::with_capacity(3 * HASH_LEN);
+    material.extend_from_slice(pre);
+    material.extend_from_slice(pred1);
+    material.extend_from_slice(pred2);
+    material
+}
+
+// Encrypts `plaintext` for the chunk at `chunk_index`, returning the ciphertext and its name.
+// `pred1`/`pred2` are the plaintext hashes of the two preceding chunks, or empty when they do not
+// exist (`resolve_preds` then fills them in).
+pub fn encode<E>(
+    scheme: EncryptionScheme,
+    chunk_index: u32,
+    plaintext: &[u8],
+    pred1: &[u8],
+    pred2: &[u8],
+) -> Result<(Vec<u8>, Vec<u8>), SelfEncryptionError<E>> {
+    let pre = pre_hash(plaintext);
+    let (pred1, pred2) = resolve_preds(&pre, pred1, pred2);
+    let material = key_material(&pre, pred1, pred2);
+    let ciphertext = match scheme {
+        EncryptionScheme::Legacy => legacy_xor(&material, plaintext),
+        EncryptionScheme::Aead => ::sequential::aead::seal(chunk_index, &material, plaintext)?,
+    };
+    let name = post_hash(&ciphertext);
+    Ok((ciphertext, name))
+}
+
+// Reverses `encode`.  `pre` is the expected plaintext hash recorded in the `DataMap`; for the
+// legacy scheme it is verified after decryption, and for the AEAD scheme the cipher's tag check
+// provides authentication.
+pub fn decode<E>(
+    scheme: EncryptionScheme,
+    chunk_index: u32,
+    ciphertext: &[u8],
+    pre: &[u8],
+    pred1: &[u8],
+    pred2: &[u8],
+) -> Result<Vec<u8>, SelfEncryptionError<E>> {
+    let (pred1, pred2) = resolve_preds(pre, pred1, pred2);
+    let material = key_material(pre, pred1, pred2);
+    match scheme {
+        EncryptionScheme::Legacy => {
+            let plaintext = legacy_xor(&material, ciphertext);
+            if pre_hash(&plaintext) != pre {
+                return Err(SelfEncryptionError::Decryption);
+            }
+            Ok(plaintext)
+        }
+        EncryptionScheme::Aead => ::sequential::aead::open(chunk_index, &material, ciphertext),
+    }
+}
+
+// A symmetric, reversible stream cipher: XOR the data with a pad and a SHA-256 derived keystream.
+// Applying it twice returns the original bytes.
+fn legacy_xor(material: &[u8], data: &[u8]) -> Vec<u8> {
+    let pad = &material[..HASH_LEN];
+    let key = &material[HASH_LEN..2 * HASH_LEN];
+    let stream = keystream(key, data.len());
+    data.iter()
+        .enumerate()
+        .map(|(i, byte)| byte ^ pad[i % pad.len()] ^ stream[i])
+        .collect()
+}
+
+// Expands `key` into a `len`-byte keystream by hashing `key || counter` blocks.
+fn keystream(key: &[u8], len: usize) -> Vec<u8> {
+    let mut stream = Vec::with_capacity(len);
+    let mut counter: u64 = 0;
+    while stream.len() < len {
+        let mut block = Vec::with_capacity(key.len() + 8);
+        block.extend_from_slice(key);
+        block.extend_from_slice(&counter.to_le_bytes());
+        stream.extend_from_slice(digest(&SHA256, &block).as_ref());
+        counter += 1;
+    }
+    stream.truncate(len);
+    stream
+}
@@ -0,0 +1,521 @@
+// Copyright 2018 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under The General Public License (GPL), version 3.
+// Unless required by applicable law or agreed to in writing, the SAFE Network Software distributed
+// under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied. Please review the Licences for the specific language governing
+// permissions and limitations relating to use of the SAFE Network Software.
+
+//! C ABI surface allowing non-Rust callers (C, Swift, Node native addons) to encrypt and decrypt
+//! through an injectable storage backend supplied as C function pointers.
+//!
+//! Buffers cross the boundary as a length + pointer pair (`ByteArray`); any buffer handed back to
+//! the caller is owned by them and must be released with `self_encryption_free`.  Errors are
+//! flattened to an integer code plus an optional message buffer, and every entry point traps
+//! panics so an unwind never crosses the boundary.
+//!
+//! As well as the one-shot `self_encryption_encrypt`/`self_encryption_decrypt` helpers, the module
+//! exposes opaque streaming handles mirroring the `EncryptWriter`/`SelfEncryptor` chain:
+//! `EncryptorHandle` feeds data in incrementally (promoting small -> medium -> large internally)
+//! and `DecryptorHandle` reads arbitrary plaintext windows on demand, so a caller can process an
+//! arbitrarily large blob without materialising it whole on either side.
+
+use data_map::DataMap;
+use futures::{future, Future};
+use maidsafe_utilities::serialisation::{deserialise, serialise};
+use self_encryptor::SelfEncryptor;
+use sequential::{ChunkConfig, EncryptWriter};
+use std::io::Write;
+use std::os::raw::c_void;
+use std::panic;
+use std::slice;
+use util::{BoxFuture, FutureExt};
+use {SelfEncryptionError, Storage, StorageError};
+
+// Status codes returned across the FFI boundary.  `0` is success; the negatives mirror the
+// `SelfEncryptionError` variants so a caller can branch on the failure class.
+pub const FFI_OK: i32 = 0;
+pub const FFI_ERR_COMPRESSION: i32 = -1;
+pub const FFI_ERR_DECRYPTION: i32 = -2;
+pub const FFI_ERR_IO: i32 = -3;
+pub const FFI_ERR_STORAGE: i32 = -4;
+pub const FFI_ERR_GENERATOR: i32 = -5;
+pub const FFI_ERR_INTEGRITY: i32 = -6;
+pub const FFI_ERR_PANIC: i32 = -100;
+
+// A pointer + length + capacity triple mapping a Rust `Vec<u8>` to something C can read and later
+// free.  A `ByteArray` returned from this module owns its allocation until passed to
+// `self_encryption_free`; the `cap` field carries the real allocation size so the buffer is freed
+// with the exact layout it was allocated with, avoiding an allocator size mismatch.
+#[repr(C)]
+pub struct ByteArray {
+    pub ptr: *mut u8,
+    pub len: usize,
+    pub cap: usize,
+}
+
+impl ByteArray {
+    fn from_vec(mut data: Vec<u8>) -> ByteArray {
+        let array = ByteArray {
+            ptr: data.as_mut_ptr(),
+            len: data.len(),
+            cap: data.capacity(),
+        };
+        ::std::mem::forget(data);
+        array
+    }
+
+    fn empty() -> ByteArray {
+        ByteArray {
+            ptr: ::std::ptr::null_mut(),
+            len: 0,
+            cap: 0,
+        }
+    }
+}
+
+// Storage callbacks registered by the caller.  `get` writes the fetched chunk into `out` and
+// returns `FFI_OK`; `put` stores the supplied chunk.  Both receive the opaque `ctx` the caller
+// passed to the entry point so they can locate their own backend.
+//
+// Ownership contract for `get`: the callback points `out` at a buffer it continues to own and
+// keeps valid for the duration of the call.  This module copies the bytes out immediately and does
+// NOT take ownership or free the buffer - the callee remains responsible for releasing it.  (Only
+// buffers handed back to the caller BY this module, such as the encoded `DataMap`, are freed with
+// `self_encryption_free`.)
+pub type GetFn =
+    extern "C" fn(ctx: *mut c_void, name: *const u8, name_len: usize, out: *mut ByteArray) -> i32;
+pub type PutFn = extern "C" fn(
+    ctx: *mut c_void,
+    name: *const u8,
+    name_len: usize,
+    data: *const u8,
+    data_len: usize,
+) -> i32;
+
+// Error surfaced by `FfiStorage` when a registered callback reports failure.
+#[derive(Debug)]
+pub struct FfiStorageError(i32);
+
+impl ::std::fmt::Display for FfiStorageError {
+    fn fmt(&self, formatter: &mut ::std::fmt::Formatter) -> ::std::fmt::Result {
+        write!(formatter, "storage callback returned {}", self.0)
+    }
+}
+
+impl ::std::error::Error for FfiStorageError {}
+
+impl StorageError for FfiStorageError {}
+
+// Adapts a pair of C callbacks into the `Storage` trait.  The callbacks are synchronous, so the
+// returned futures are already resolved.
+struct FfiStorage {
+    ctx: *mut c_void,
+    get: GetFn,
+    put: PutFn,
+}
+
+impl Storage for FfiStorage {
+    type Error = FfiStorageError;
+
+    fn get(&self, name: &[u8]) -> BoxFuture<Vec<u8>, FfiStorageError> {
+        let mut out = ByteArray::empty();
+        let code = (self.get)(self.ctx, name.as_ptr(), name.len(), &mut out);
+        if code != FFI_OK {
+            return future::err(FfiStorageError(code)).into_box();
+        }
+        let data = unsafe { owned_vec(&out) };
+        future::ok(data).into_box()
+    }
+
+    fn put(&mut self, name: Vec<u8>, data: Vec<u8>) -> BoxFuture<(), FfiStorageError> {
+        let code = (self.put)(
+            self.ctx,
+            name.as_ptr(),
+            name.len(),
+            data.as_ptr(),
+            data.len(),
+        );
+        if code != FFI_OK {
+            return future::err(FfiStorageError(code)).into_box();
+        }
+        future::ok(()).into_box()
+    }
+}
+
+// Opaque handle wrapping a streaming `EncryptWriter`, so a C caller can feed data incrementally
+// rather than handing over the whole plaintext at once.  Created by `self_encryption_encryptor_new`,
+// driven with `self_encryption_encryptor_write`, and consumed by either
+// `self_encryption_encryptor_finish` (which yields the `DataMap`) or `self_encryption_encryptor_free`
+// (to abort).  The caller only ever holds a pointer to this; its fields are never exposed.
+pub struct EncryptorHandle {
+    writer: EncryptWriter<FfiStorage>,
+}
+
+// Opaque handle wrapping a `SelfEncryptor`, so a C caller can read arbitrary plaintext windows on
+// demand instead of decrypting the entire blob up front.  Created by
+// `self_encryption_decryptor_new` and released with `self_encryption_decryptor_free`.
+pub struct DecryptorHandle {
+    encryptor: SelfEncryptor<FfiStorage>,
+}
+
+// Creates a streaming encryptor over the supplied storage callbacks, writing the opaque handle into
+// `out_handle`.  Error handling mirrors `self_encryption_encrypt`.
+#[no_mangle]
+pub extern "C" fn self_encryption_encryptor_new(
+    ctx: *mut c_void,
+    get: GetFn,
+    put: PutFn,
+    out_handle: *mut *mut EncryptorHandle,
+    err_msg: *mut ByteArray,
+) -> i32 {
+    catch(err_msg, || {
+        let storage = FfiStorage { ctx, get, put };
+        let writer = EncryptWriter::new(storage, ChunkConfig::default())?;
+        let handle = Box::new(EncryptorHandle { writer });
+        unsafe { *out_handle = Box::into_raw(handle) };
+        Ok(())
+    })
+}
+
+// Feeds `len` bytes at `data` to a streaming encryptor, flushing full chunks to storage as they
+// become available.  The handle remains valid afterwards.
+#[no_mangle]
+pub extern "C" fn self_encryption_encryptor_write(
+    handle: *mut EncryptorHandle,
+    data: *const u8,
+    len: usize,
+    err_msg: *mut ByteArray,
+) -> i32 {
+    catch(err_msg, || {
+        let handle = unsafe { &mut *handle };
+        let input = unsafe { slice::from_raw_parts(data, len) };
+        handle.writer.write_all(input)?;
+        Ok(())
+    })
+}
+
+// Finalises a streaming encryptor, writing the serialised `DataMap` into `out_data_map` and
+// consuming the handle (it must not be used again, and `self_encryption_encryptor_free` must not be
+// called on it).
+#[no_mangle]
+pub extern "C" fn self_encryption_encryptor_finish(
+    handle: *mut EncryptorHandle,
+    out_data_map: *mut ByteArray,
+    err_msg: *mut ByteArray,
+) -> i32 {
+    catch(err_msg, || {
+        let handle = unsafe { Box::from_raw(handle) };
+        let (data_map, _) = handle.writer.finish()?;
+        let serialised = serialise(&data_map).map_err(|_| SelfEncryptionError::Generator)?;
+        unsafe { *out_data_map = ByteArray::from_vec(serialised) };
+        Ok(())
+    })
+}
+
+// Releases a streaming encryptor without finalising it, discarding any unflushed tail.  Use to
+// abort an upload; a handle passed to `self_encryption_encryptor_finish` must not also be freed.
+#[no_mangle]
+pub extern "C" fn self_encryption_encryptor_free(handle: *mut EncryptorHandle) {
+    if !handle.is_null() {
+        unsafe {
+            let _ = Box::from_raw(handle);
+        }
+    }
+}
+
+// Creates a decryptor over the serialised `DataMap` at `data_map`, writing the opaque handle into
+// `out_handle`.  Error handling mirrors `self_encryption_encrypt`.
+#[no_mangle]
+pub extern "C" fn self_encryption_decryptor_new(
+    ctx: *mut c_void,
+    get: GetFn,
+    put: PutFn,
+    data_map: *const u8,
+    data_map_len: usize,
+    out_handle: *mut *mut DecryptorHandle,
+    err_msg: *mut ByteArray,
+) -> i32 {
+    catch(err_msg, || {
+        let bytes = unsafe { slice::from_raw_parts(data_map, data_map_len) };
+        let data_map: DataMap = deserialise(bytes).map_err(|_| SelfEncryptionError::Generator)?;
+        let storage = FfiStorage { ctx, get, put };
+        let encryptor = SelfEncryptor::new(storage, data_map)?;
+        let handle = Box::new(DecryptorHandle { encryptor });
+        unsafe { *out_handle = Box::into_raw(handle) };
+        Ok(())
+    })
+}
+
+// The total plaintext length described by the decryptor's `DataMap`.
+#[no_mangle]
+pub extern "C" fn self_encryption_decryptor_len(handle: *const DecryptorHandle) -> u64 {
+    let handle = unsafe { &*handle };
+    handle.encryptor.len()
+}
+
+// Reads `len` plaintext bytes starting at `offset`, fetching and decrypting only the chunks that
+// window touches, and writes them into `out`.  Error handling mirrors `self_encryption_encrypt`.
+#[no_mangle]
+pub extern "C" fn self_encryption_decryptor_read(
+    handle: *const DecryptorHandle,
+    offset: u64,
+    len: u64,
+    out: *mut ByteArray,
+    err_msg: *mut ByteArray,
+) -> i32 {
+    catch(err_msg, || {
+        let handle = unsafe { &*handle };
+        let plaintext = handle.encryptor.read(offset, len).wait()?;
+        unsafe { *out = ByteArray::from_vec(plaintext) };
+        Ok(())
+    })
+}
+
+// Releases a decryptor handle.
+#[no_mangle]
+pub extern "C" fn self_encryption_decryptor_free(handle: *mut DecryptorHandle) {
+    if !handle.is_null() {
+        unsafe {
+            let _ = Box::from_raw(handle);
+        }
+    }
+}
+
+// Encrypts `len` bytes at `data` using the supplied storage callbacks, writing the serialised
+// `DataMap` into `out_data_map`.  Returns `FFI_OK` on success, otherwise an `FFI_ERR_*` code; when
+// a message is available it is written into `err_msg` as an owned buffer the caller must free.
+#[no_mangle]
+pub extern "C" fn self_encryption_encrypt(
+    ctx: *mut c_void,
+    get: GetFn,
+    put: PutFn,
+    data: *const u8,
+    len: usize,
+    out_data_map: *mut ByteArray,
+    err_msg: *mut ByteArray,
+) -> i32 {
+    catch(err_msg, || {
+        let input = unsafe { slice::from_raw_parts(data, len) }.to_vec();
+        let storage = FfiStorage { ctx, get, put };
+        let mut encryptor = SelfEncryptor::new(storage, DataMap::None)?;
+        encryptor.write(&input, 0).wait()?;
+        let (data_map, _) = encryptor.close().wait()?;
+        let serialised = serialise(&data_map).map_err(|_| SelfEncryptionError::Generator)?;
+        unsafe { *out_data_map = ByteArray::from_vec(serialised) };
+        Ok(())
+    })
+}
+
+// Decrypts the data described by the serialised `DataMap` at `data_map`, writing the plaintext
+// into `out`.  Error handling mirrors `self_encryption_encrypt`.
+#[no_mangle]
+pub extern "C" fn self_encryption_decrypt(
+    ctx: *mut c_void,
+    get: GetFn,
+    put: PutFn,
+    data_map: *const u8,
+    data_map_len: usize,
+    out: *mut ByteArray,
+    err_msg: *mut ByteArray,
+) -> i32 {
+    catch(err_msg, || {
+        let bytes = unsafe { slice::from_raw_parts(data_map, data_map_len) };
+        let data_map: DataMap = deserialise(bytes).map_err(|_| SelfEncryptionError::Generator)?;
+        let storage = FfiStorage { ctx, get, put };
+        let encryptor = SelfEncryptor::new(storage, data_map)?;
+        let length = encryptor.len();
+        let plaintext = encryptor.read(0, length).wait()?;
+        unsafe { *out = ByteArray::from_vec(plaintext) };
+        Ok(())
+    })
+}
+
+// Releases a buffer previously handed to the caller by this module.
+#[no_mangle]
+pub extern "C" fn self_encryption_free(array: ByteArray) {
+    if !array.ptr.is_null() {
+        unsafe {
+            let _ = Vec::from_raw_parts(array.ptr, array.len, array.cap);
+        }
+    }
+}
+
+// Runs `body`, flattening any `SelfEncryptionError` into a status code (and an optional message in
+// `err_msg`) and trapping panics so nothing unwinds across the boundary.
+fn catch<F>(err_msg: *mut ByteArray, body: F) -> i32
+where
+    F: FnOnce() -> Result<(), SelfEncryptionError<FfiStorageError>> + panic::UnwindSafe,
+{
+    match panic::catch_unwind(body) {
+        Ok(Ok(())) => FFI_OK,
+        Ok(Err(error)) => {
+            write_message(err_msg, &format!("{}", error));
+            code_for(&error)
+        }
+        Err(_) => {
+            write_message(err_msg, "panic in self_encryption FFI");
+            FFI_ERR_PANIC
+        }
+    }
+}
+
+// Maps a `SelfEncryptionError` variant onto its `FFI_ERR_*` code.
+fn code_for(error: &SelfEncryptionError<FfiStorageError>) -> i32 {
+    match *error {
+        SelfEncryptionError::Compression => FFI_ERR_COMPRESSION,
+        SelfEncryptionError::Decryption => FFI_ERR_DECRYPTION,
+        SelfEncryptionError::Io(_) => FFI_ERR_IO,
+        SelfEncryptionError::Storage(_) => FFI_ERR_STORAGE,
+        SelfEncryptionError::Generator => FFI_ERR_GENERATOR,
+        SelfEncryptionError::IntegrityFailure { .. } => FFI_ERR_INTEGRITY,
+    }
+}
+
+fn write_message(err_msg: *mut ByteArray, message: &str) {
+    if !err_msg.is_null() {
+        unsafe { *err_msg = ByteArray::from_vec(message.as_bytes().to_vec()) };
+    }
+}
+
+// Copies a caller-provided `ByteArray` into an owned `Vec`.  Used for `get`, where the buffer is
+// owned by the caller's backend rather than by us.
+unsafe fn owned_vec(array: &ByteArray) -> Vec<u8> {
+    if array.ptr.is_null() {
+        Vec::new()
+    } else {
+        slice::from_raw_parts(array.ptr, array.len).to_vec()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    // A `get` callback which points `out` at the backend's own buffer (which it keeps owning) per
+    // the ownership contract, rather than allocating for the caller.
+    extern "C" fn test_get(ctx: *mut c_void, name: *const u8, name_len: usize, out: *mut ByteArray) -> i32 {
+        let map = unsafe { &*(ctx as *const HashMap<Vec<u8>, Vec<u8>>) };
+        let key = unsafe { slice::from_raw_parts(name, name_len) }.to_vec();
+        match map.get(&key) {
+            Some(data) => {
+                unsafe {
+                    *out = ByteArray {
+                        ptr: data.as_ptr() as *mut u8,
+                        len: data.len(),
+                        cap: 0,
+                    }
+                };
+                FFI_OK
+            }
+            None => FFI_ERR_STORAGE,
+        }
+    }
+
+    extern "C" fn test_put(
+        ctx: *mut c_void,
+        name: *const u8,
+        name_len: usize,
+        data: *const u8,
+        data_len: usize,
+    ) -> i32 {
+        let map = unsafe { &mut *(ctx as *mut HashMap<Vec<u8>, Vec<u8>>) };
+        let key = unsafe { slice::from_raw_parts(name, name_len) }.to_vec();
+        let value = unsafe { slice::from_raw_parts(data, data_len) }.to_vec();
+        let _ = map.insert(key, value);
+        FFI_OK
+    }
+
+    #[test]
+    fn encrypt_decrypt_round_trip() {
+        let mut map: HashMap<Vec<u8>, Vec<u8>> = HashMap::new();
+        let ctx = &mut map as *mut HashMap<Vec<u8>, Vec<u8>> as *mut c_void;
+        let data: Vec<u8> = (0..4096).map(|i| (i % 251) as u8).collect();
+
+        let mut data_map = ByteArray::empty();
+        let mut err = ByteArray::empty();
+        let code = self_encryption_encrypt(
+            ctx,
+            test_get,
+            test_put,
+            data.as_ptr(),
+            data.len(),
+            &mut data_map,
+            &mut err,
+        );
+        assert_eq!(code, FFI_OK);
+
+        let mut out = ByteArray::empty();
+        let code = self_encryption_decrypt(
+            ctx,
+            test_get,
+            test_put,
+            data_map.ptr,
+            data_map.len,
+            &mut out,
+            &mut err,
+        );
+        assert_eq!(code, FFI_OK);
+
+        let decrypted = unsafe { slice::from_raw_parts(out.ptr, out.len) };
+        assert_eq!(decrypted, &data[..]);
+
+        self_encryption_free(out);
+        self_encryption_free(data_map);
+    }
+
+    #[test]
+    fn streaming_handle_round_trip() {
+        let mut map: HashMap<Vec<u8>, Vec<u8>> = HashMap::new();
+        let ctx = &mut map as *mut HashMap<Vec<u8>, Vec<u8>> as *mut c_void;
+        let data: Vec<u8> = (0..10_000).map(|i| (i % 251) as u8).collect();
+
+        // Encrypt incrementally through the opaque handle rather than in one shot.
+        let mut encryptor = ::std::ptr::null_mut();
+        let mut err = ByteArray::empty();
+        assert_eq!(
+            self_encryption_encryptor_new(ctx, test_get, test_put, &mut encryptor, &mut err),
+            FFI_OK
+        );
+        for piece in data.chunks(1000) {
+            assert_eq!(
+                self_encryption_encryptor_write(encryptor, piece.as_ptr(), piece.len(), &mut err),
+                FFI_OK
+            );
+        }
+        let mut data_map = ByteArray::empty();
+        assert_eq!(
+            self_encryption_encryptor_finish(encryptor, &mut data_map, &mut err),
+            FFI_OK
+        );
+
+        // Decrypt a single window through the decryptor handle without materialising the whole blob.
+        let mut decryptor = ::std::ptr::null_mut();
+        assert_eq!(
+            self_encryption_decryptor_new(
+                ctx,
+                test_get,
+                test_put,
+                data_map.ptr,
+                data_map.len,
+                &mut decryptor,
+                &mut err,
+            ),
+            FFI_OK
+        );
+        assert_eq!(self_encryption_decryptor_len(decryptor), data.len() as u64);
+
+        let mut out = ByteArray::empty();
+        assert_eq!(
+            self_encryption_decryptor_read(decryptor, 1500, 500, &mut out, &mut err),
+            FFI_OK
+        );
+        let window = unsafe { slice::from_raw_parts(out.ptr, out.len) };
+        assert_eq!(window, &data[1500..2000]);
+
+        self_encryption_free(out);
+        self_encryption_free(data_map);
+        self_encryption_decryptor_free(decryptor);
+    }
+}
@@ -0,0 +1,112 @@
+// Copyright 2018 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under The General Public License (GPL), version 3.
+// Unless required by applicable law or agreed to in writing, the SAFE Network Software distributed
+// under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied. Please review the Licences for the specific language governing
+// permissions and limitations relating to use of the SAFE Network Software.
+
+//! A file content self-encryptor.
+//!
+//! Data is split into fixed-size chunks whose encryption keys are derived from neighbouring
+//! chunks' hashes, so the ciphertext cannot be decrypted without the `DataMap` describing it.
+
+extern crate futures;
+extern crate ring;
+#[macro_use]
+extern crate serde_derive;
+extern crate maidsafe_utilities;
+
+#[cfg(test)]
+extern crate itertools;
+#[cfg(test)]
+extern crate rand;
+#[cfg(test)]
+#[macro_use]
+extern crate unwrap;
+
+use std::error::Error;
+use std::fmt::{self, Debug, Display};
+use std::io;
+
+pub use data_map::{ChunkDetails, DataMap};
+pub use self_encryptor::SelfEncryptor;
+pub use sequential::{ChunkConfig, EncryptionScheme};
+
+mod encryption;
+mod partial_write;
+mod util;
+
+pub mod data_map;
+pub mod ffi;
+pub mod self_encryptor;
+pub mod sequential;
+
+#[cfg(test)]
+mod test_helpers;
+#[cfg(test)]
+mod utils;
+
+/// The default chunk size, in bytes.  A `ChunkConfig` may select a different size within the
+/// supported range (see `sequential::chunk_config`).
+pub const MIN_CHUNK_SIZE: u32 = 1024;
+
+/// Errors which can arise whilst self-encrypting or -decrypting data.  `E` is the error type of
+/// the backing `Storage`.
+#[derive(Debug)]
+pub enum SelfEncryptionError<E> {
+    /// A compression step failed.
+    Compression,
+    /// A chunk could not be decrypted - its plaintext hash did not match the `DataMap`.
+    Decryption,
+    /// An I/O error occurred.
+    Io(io::Error),
+    /// The backing storage returned an error.
+    Storage(E),
+    /// An internal generator or configuration value was out of range.
+    Generator,
+    /// A fetched chunk failed its authentication check under the AEAD scheme.  Carries the index
+    /// of the offending chunk so a caller can locate the corrupted or substituted data.
+    IntegrityFailure {
+        /// Index of the chunk whose authentication tag did not verify.
+        chunk_index: u32,
+    },
+}
+
+impl<E: Display> Display for SelfEncryptionError<E> {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            SelfEncryptionError::Compression => write!(formatter, "compression error"),
+            SelfEncryptionError::Decryption => write!(formatter, "decryption error"),
+            SelfEncryptionError::Io(ref error) => write!(formatter, "I/O error: {}", error),
+            SelfEncryptionError::Storage(ref error) => write!(formatter, "storage error: {}", error),
+            SelfEncryptionError::Generator => write!(formatter, "generator error"),
+            SelfEncryptionError::IntegrityFailure { chunk_index } => {
+                write!(formatter, "integrity failure in chunk {}", chunk_index)
+            }
+        }
+    }
+}
+
+impl<E: Debug + Display> Error for SelfEncryptionError<E> {}
+
+impl<E> From<io::Error> for SelfEncryptionError<E> {
+    fn from(error: io::Error) -> SelfEncryptionError<E> {
+        SelfEncryptionError::Io(error)
+    }
+}
+
+/// Marker trait for the error type a `Storage` implementation returns.
+pub trait StorageError: Debug + Display + Send + 'static {}
+
+/// A pluggable backend responsible for persisting and retrieving encrypted chunks by name.
+pub trait Storage {
+    /// The error type returned by this backend.
+    type Error: StorageError;
+
+    /// Retrieves the chunk previously stored under `name`.
+    fn get(&self, name: &[u8]) -> util::BoxFuture<Vec<u8>, Self::Error>;
+
+    /// Stores `data` under `name`.
+    fn put(&mut self, name: Vec<u8>, data: Vec<u8>) -> util::BoxFuture<(), Self::Error>;
+}
@@ -0,0 +1,157 @@
+// Copyright 2018 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under The General Public License (GPL), version 3.
+// Unless required by applicable law or agreed to in writing, the SAFE Network Software distributed
+// under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied. Please review the Licences for the specific language governing
+// permissions and limitations relating to use of the SAFE Network Software.
+
+//! In-place partial editing for `SelfEncryptor`.
+//!
+//! A chunk's key is derived from the plaintext hashes of itself and its two predecessors, so
+//! changing chunk `i` invalidates only chunks `i`, `i + 1` and `i + 2`.  `write_at` therefore
+//! re-emits just the chunks intersecting the edit plus those two dependent neighbours, reusing the
+//! untouched chunks' `DataMap` entries by their existing hashes so storage dedup still holds.
+//! Edits that grow the file, or that cross the small/medium/large boundary, fall back to a full
+//! re-encode over the materialised plaintext, which remains correct.
+
+use data_map::ChunkDetails;
+use encryption;
+use futures::{future, Future};
+use self_encryptor::SelfEncryptor;
+use util::{BoxFuture, FutureExt};
+use {SelfEncryptionError, Storage};
+
+// Number of later chunks whose key references a given chunk's hash.
+const DEPENDENT_NEIGHBOURS: usize = 2;
+
+impl<S> SelfEncryptor<S>
+where
+    S: Storage + 'static,
+{
+    /// Overwrites `data.len()` bytes at `offset`, re-encrypting only the affected chunks and their
+    /// two dependent neighbours; all other chunks are left referenced unchanged in the `DataMap`.
+    /// Edits that grow the file fall back to a full re-encode so a small file can grow across the
+    /// chunking boundary correctly.
+    pub fn write_at(&mut self, offset: u64, data: &[u8]) -> BoxFuture<(), SelfEncryptionError<S::Error>> {
+        let result = self.write_at_sync(offset, data);
+        match result {
+            Ok(()) => future::ok(()).into_box(),
+            Err(error) => future::err(error).into_box(),
+        }
+    }
+
+    /// Shrinks the data to `len` bytes, re-encrypting only the chunk which now holds the new end.
+    /// When the data is already buffered, or `len` is below three chunks, the truncation is applied
+    /// to the materialised plaintext instead and `close` re-encodes it (or returns it inline).
+    pub fn truncate(&mut self, len: u64) -> BoxFuture<(), SelfEncryptionError<S::Error>> {
+        let result = self.truncate_sync(len);
+        match result {
+            Ok(()) => future::ok(()).into_box(),
+            Err(error) => future::err(error).into_box(),
+        }
+    }
+
+    fn write_at_sync(&mut self, offset: u64, data: &[u8]) -> Result<(), SelfEncryptionError<S::Error>> {
+        // An empty edit is a no-op; returning early also keeps the `end - 1` below from underflowing.
+        if data.is_empty() {
+            return Ok(());
+        }
+        let end = offset
+            .checked_add(data.len() as u64)
+            .ok_or(SelfEncryptionError::Generator)?;
+        let minimal = self.buffer.is_none() && !self.chunks.is_empty() && end <= self.file_size;
+        if !minimal {
+            // Growth, inline content, or an empty map: materialise and splice; `close` re-encodes.
+            return self.write(data, offset).wait();
+        }
+
+        let starts = self.chunk_start_offsets();
+        let first = self.chunk_index_at(&starts, offset);
+        let last = self.chunk_index_at(&starts, end - 1);
+        let window_end = (last + DEPENDENT_NEIGHBOURS).min(self.chunks.len() - 1);
+
+        // Decrypt the window chunks and splice the edit into their concatenated plaintext.
+        let window_start_byte = starts[first];
+        let mut plaintext = Vec::new();
+        for index in first..=window_end {
+            plaintext.extend_from_slice(&self.fetch_chunk(index)?);
+        }
+        let edit_at = (offset - window_start_byte) as usize;
+        plaintext[edit_at..edit_at + data.len()].copy_from_slice(data);
+
+        // Re-emit each window chunk at its original size, reusing unchanged predecessors' hashes.
+        let mut cursor = 0usize;
+        for index in first..=window_end {
+            let size = self.chunks[index].source_size as usize;
+            let chunk = &plaintext[cursor..cursor + size];
+            self.reemit_chunk(index, chunk)?;
+            cursor += size;
+        }
+        Ok(())
+    }
+
+    fn truncate_sync(&mut self, len: u64) -> Result<(), SelfEncryptionError<S::Error>> {
+        if len >= self.file_size {
+            return Ok(());
+        }
+        if self.buffer.is_some() || len < 3 * self.chunk_size {
+            // Collapsing back towards inline content, or already buffered: do it in memory.
+            self.materialise()?;
+            let buffer = self.buffer.as_mut().expect("materialised above");
+            buffer.truncate(len as usize);
+            self.file_size = len;
+            return Ok(());
+        }
+
+        let starts = self.chunk_start_offsets();
+        let boundary = self.chunk_index_at(&starts, len - 1);
+        let plaintext = self.fetch_chunk(boundary)?;
+        let keep = (len - starts[boundary]) as usize;
+        let shortened = plaintext[..keep].to_vec();
+
+        self.chunks.truncate(boundary + 1);
+        self.reemit_chunk(boundary, &shortened)?;
+        self.file_size = len;
+        Ok(())
+    }
+
+    // Re-encrypts `chunk` as the chunk at `index`, stores the new ciphertext and updates the
+    // chunk's `DataMap` entry in place.  Predecessors are read from `self.chunks`, which has
+    // already been updated for earlier indices in the window.
+    fn reemit_chunk(&mut self, index: usize, chunk: &[u8]) -> Result<(), SelfEncryptionError<S::Error>> {
+        let (pred1, pred2) = self.predecessor_hashes(index);
+        let (ciphertext, name) =
+            encryption::encode(self.scheme, index as u32, chunk, &pred1, &pred2)?;
+        self.storage
+            .put(name.clone(), ciphertext)
+            .wait()
+            .map_err(SelfEncryptionError::Storage)?;
+        self.chunks[index] = ChunkDetails {
+            chunk_num: index as u32,
+            hash: name,
+            pre_hash: encryption::pre_hash(chunk),
+            source_size: chunk.len() as u64,
+        };
+        Ok(())
+    }
+
+    // The absolute start offset of each chunk, derived from the chunks' source sizes.
+    fn chunk_start_offsets(&self) -> Vec<u64> {
+        let mut starts = Vec::with_capacity(self.chunks.len());
+        let mut offset = 0;
+        for chunk in &self.chunks {
+            starts.push(offset);
+            offset += chunk.source_size;
+        }
+        starts
+    }
+
+    // The index of the chunk containing byte `position`.
+    fn chunk_index_at(&self, starts: &[u64], position: u64) -> usize {
+        match starts.binary_search(&position) {
+            Ok(index) => index,
+            Err(index) => index - 1,
+        }
+    }
+}
@@ -0,0 +1,449 @@
+// Copyright 2018 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under The General Public License (GPL), version 3.
+// Unless required by applicable law or agreed to in writing, the SAFE Network Software distributed
+// under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied. Please review the Licences for the specific language governing
+// permissions and limitations relating to use of the SAFE Network Software.
+
+use data_map::{ChunkDetails, DataMap};
+use encryption;
+use futures::{future, Future};
+use sequential::{ChunkConfig, EncryptionScheme};
+use util::{BoxFuture, FutureExt};
+use {SelfEncryptionError, Storage};
+
+/// Reads and writes data which has been, or is to be, self-encrypted.
+///
+/// Constructed from a `DataMap`, which records the `chunk_size` and `scheme` the data was written
+/// with, so the correct chunk boundaries and decryption path are reconstructed on read without the
+/// caller tracking the original `ChunkConfig`.
+pub struct SelfEncryptor<S> {
+    pub(crate) storage: S,
+    pub(crate) chunks: Vec<ChunkDetails>,
+    pub(crate) chunk_size: u64,
+    pub(crate) scheme: EncryptionScheme,
+    pub(crate) file_size: u64,
+    // The full plaintext, materialised for inline content and after a buffered write.  `None` when
+    // the encryptor is backed purely by stored chunks.
+    pub(crate) buffer: Option<Vec<u8>>,
+}
+
+impl<S> SelfEncryptor<S>
+where
+    S: Storage + 'static,
+{
+    /// Creates an encryptor over the data described by `data_map`.
+    pub fn new(storage: S, data_map: DataMap) -> Result<SelfEncryptor<S>, SelfEncryptionError<S::Error>> {
+        let default = ChunkConfig::default();
+        let encryptor = match data_map {
+            DataMap::Chunks {
+                chunks,
+                chunk_size,
+                scheme,
+            } => {
+                let file_size = chunks.iter().map(|chunk| chunk.source_size).sum();
+                SelfEncryptor {
+                    storage,
+                    chunks,
+                    chunk_size,
+                    scheme,
+                    file_size,
+                    buffer: None,
+                }
+            }
+            DataMap::Content(content) => {
+                let file_size = content.len() as u64;
+                SelfEncryptor {
+                    storage,
+                    chunks: vec![],
+                    chunk_size: default.chunk_size(),
+                    scheme: default.scheme(),
+                    file_size,
+                    buffer: Some(content),
+                }
+            }
+            DataMap::None => SelfEncryptor {
+                storage,
+                chunks: vec![],
+                chunk_size: default.chunk_size(),
+                scheme: default.scheme(),
+                file_size: 0,
+                buffer: Some(vec![]),
+            },
+        };
+        Ok(encryptor)
+    }
+
+    /// Creates an empty encryptor which will write new data using `config`, selecting the chunk
+    /// size and per-chunk encryption scheme.  The chosen scheme is recorded in the `DataMap`
+    /// produced by `close`, so reads reconstruct the matching decryption path.
+    pub fn with_config(storage: S, config: ChunkConfig) -> SelfEncryptor<S> {
+        SelfEncryptor {
+            storage,
+            chunks: vec![],
+            chunk_size: config.chunk_size(),
+            scheme: config.scheme(),
+            file_size: 0,
+            buffer: Some(vec![]),
+        }
+    }
+
+    /// The current length of the data, in bytes.
+    pub fn len(&self) -> u64 {
+        self.file_size
+    }
+
+    /// Returns `true` if the data is empty.
+    pub fn is_empty(&self) -> bool {
+        self.file_size == 0
+    }
+
+    /// Reads `length` bytes starting at `position`, fetching and decrypting only the chunks which
+    /// overlap the requested window.
+    pub fn read(&self, position: u64, length: u64) -> BoxFuture<Vec<u8>, SelfEncryptionError<S::Error>> {
+        let result = self.read_sync(position, length);
+        match result {
+            Ok(data) => future::ok(data).into_box(),
+            Err(error) => future::err(error).into_box(),
+        }
+    }
+
+    // Synchronous body of `read`.  Storage backends in this crate resolve their futures
+    // immediately, so the gets are driven to completion here and the result wrapped back into a
+    // future by the caller.
+    fn read_sync(&self, position: u64, length: u64) -> Result<Vec<u8>, SelfEncryptionError<S::Error>> {
+        let end = (position + length).min(self.file_size);
+        if end <= position {
+            return Ok(vec![]);
+        }
+        if let Some(ref buffer) = self.buffer {
+            return Ok(buffer[position as usize..end as usize].to_vec());
+        }
+
+        let mut output = Vec::with_capacity((end - position) as usize);
+        let mut chunk_start = 0u64;
+        for index in 0..self.chunks.len() {
+            let chunk_end = chunk_start + self.chunks[index].source_size;
+            if chunk_end > position && chunk_start < end {
+                let plaintext = self.fetch_chunk(index)?;
+                let from = position.saturating_sub(chunk_start) as usize;
+                let to = (end - chunk_start).min(plaintext.len() as u64) as usize;
+                output.extend_from_slice(&plaintext[from..to]);
+            }
+            chunk_start = chunk_end;
+            if chunk_start >= end {
+                break;
+            }
+        }
+        Ok(output)
+    }
+
+    // Fetches and decrypts the chunk at `index`, verifying its integrity.
+    pub(crate) fn fetch_chunk(&self, index: usize) -> Result<Vec<u8>, SelfEncryptionError<S::Error>> {
+        let details = &self.chunks[index];
+        let ciphertext = self
+            .storage
+            .get(&details.hash)
+            .wait()
+            .map_err(SelfEncryptionError::Storage)?;
+        let (pred1, pred2) = self.predecessor_hashes(index);
+        encryption::decode(
+            self.scheme,
+            details.chunk_num,
+            &ciphertext,
+            &details.pre_hash,
+            &pred1,
+            &pred2,
+        )
+    }
+
+    // The plaintext hashes of the two chunks preceding `index`, or empty slices when they do not
+    // exist (matching the key derivation used when the chunks were written).
+    pub(crate) fn predecessor_hashes(&self, index: usize) -> (Vec<u8>, Vec<u8>) {
+        let pred1 = if index >= 1 {
+            self.chunks[index - 1].pre_hash.clone()
+        } else {
+            vec![]
+        };
+        let pred2 = if index >= 2 {
+            self.chunks[index - 2].pre_hash.clone()
+        } else {
+            vec![]
+        };
+        (pred1, pred2)
+    }
+
+    /// Overwrites the data at `position` with `data`, materialising the plaintext on first write.
+    /// For edits against a stored `DataMap` that do not grow the file, prefer `write_at`, which
+    /// re-encrypts only the affected chunks.
+    pub fn write(&mut self, data: &[u8], position: u64) -> BoxFuture<(), SelfEncryptionError<S::Error>> {
+        let result = self.buffer_write(data, position);
+        match result {
+            Ok(()) => future::ok(()).into_box(),
+            Err(error) => future::err(error).into_box(),
+        }
+    }
+
+    // Splices `data` into the in-memory buffer, materialising it from stored chunks if necessary.
+    fn buffer_write(&mut self, data: &[u8], position: u64) -> Result<(), SelfEncryptionError<S::Error>> {
+        self.materialise()?;
+        let buffer = self.buffer.as_mut().expect("buffer materialised above");
+        let end = position as usize + data.len();
+        if buffer.len() < end {
+            buffer.resize(end, 0);
+        }
+        buffer[position as usize..end].copy_from_slice(data);
+        self.file_size = buffer.len() as u64;
+        Ok(())
+    }
+
+    // Ensures `self.buffer` holds the full plaintext, decrypting all stored chunks on first need.
+    pub(crate) fn materialise(&mut self) -> Result<(), SelfEncryptionError<S::Error>> {
+        if self.buffer.is_some() {
+            return Ok(());
+        }
+        let mut plaintext = Vec::with_capacity(self.file_size as usize);
+        for index in 0..self.chunks.len() {
+            plaintext.extend_from_slice(&self.fetch_chunk(index)?);
+        }
+        self.file_size = plaintext.len() as u64;
+        self.buffer = Some(plaintext);
+        Ok(())
+    }
+
+    /// Finalises the encryptor, writing any buffered data as chunks and returning the `DataMap`.
+    pub fn close(mut self) -> BoxFuture<(DataMap, S), SelfEncryptionError<S::Error>> {
+        let result = self.close_sync();
+        match result {
+            Ok(data_map) => future::ok((data_map, self.storage)).into_box(),
+            Err(error) => future::err(error).into_box(),
+        }
+    }
+
+    // Synchronous body of `close`.
+    fn close_sync(&mut self) -> Result<DataMap, SelfEncryptionError<S::Error>> {
+        let buffer = match self.buffer.take() {
+            Some(buffer) => buffer,
+            // No buffered write: the stored chunks, including any re-emitted in place by
+            // `write_at` or `truncate`, are current, so hand back a map describing them as they are.
+            None => {
+                return Ok(DataMap::Chunks {
+                    chunks: self.chunks.clone(),
+                    chunk_size: self.chunk_size,
+                    scheme: self.scheme,
+                })
+            }
+        };
+
+        if (buffer.len() as u64) < 3 * self.chunk_size {
+            return Ok(DataMap::Content(buffer));
+        }
+
+        let chunks = self.encode_all(&buffer)?;
+        Ok(DataMap::Chunks {
+            chunks,
+            chunk_size: self.chunk_size,
+            scheme: self.scheme,
+        })
+    }
+
+    // Splits `buffer` into `chunk_size` pieces and encrypts each, storing the ciphertext and
+    // returning the chunk details.  Uses the same key derivation as the streaming encryptors so a
+    // buffered close and a streamed write of the same data yield identical maps.
+    pub(crate) fn encode_all(
+        &mut self,
+        buffer: &[u8],
+    ) -> Result<Vec<ChunkDetails>, SelfEncryptionError<S::Error>> {
+        let chunk_size = self.chunk_size as usize;
+        let mut chunks: Vec<ChunkDetails> = Vec::new();
+        let mut offset = 0;
+        while offset < buffer.len() {
+            let end = (offset + chunk_size).min(buffer.len());
+            let plaintext = &buffer[offset..end];
+            let index = chunks.len() as u32;
+            let pred1 = chunks.last().map(|c| c.pre_hash.clone()).unwrap_or_default();
+            let pred2 = if chunks.len() >= 2 {
+                chunks[chunks.len() - 2].pre_hash.clone()
+            } else {
+                vec![]
+            };
+            let (ciphertext, name) =
+                encryption::encode(self.scheme, index, plaintext, &pred1, &pred2)?;
+            self.storage
+                .put(name.clone(), ciphertext)
+                .wait()
+                .map_err(SelfEncryptionError::Storage)?;
+            chunks.push(ChunkDetails {
+                chunk_num: index,
+                hash: name,
+                pre_hash: encryption::pre_hash(plaintext),
+                source_size: plaintext.len() as u64,
+            });
+            offset = end;
+        }
+        Ok(chunks)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use data_map::DataMap;
+    use itertools::Itertools;
+    use maidsafe_utilities::SeededRng;
+    use rand::Rng;
+    use sequential::EncryptionScheme;
+    use test_helpers::{Blob, SimpleStorage};
+
+    // Encrypts `data` from an empty map and returns the resulting `DataMap` and storage.
+    fn encrypt(data: &[u8]) -> (DataMap, SimpleStorage) {
+        let mut encryptor = unwrap!(SelfEncryptor::new(SimpleStorage::new(), DataMap::None));
+        unwrap!(encryptor.write(data, 0).wait());
+        unwrap!(encryptor.close().wait())
+    }
+
+    #[test]
+    fn chunked_round_trip() {
+        let mut rng = SeededRng::new();
+        let data = rng.gen_iter().take(4096).collect_vec();
+        let (data_map, storage) = encrypt(&data);
+        match data_map {
+            DataMap::Chunks { ref chunks, .. } => assert!(chunks.len() >= 3),
+            _ => panic!("expected a chunked DataMap"),
+        }
+        let encryptor = unwrap!(SelfEncryptor::new(storage, data_map));
+        assert_eq!(encryptor.len(), data.len() as u64);
+        let whole = unwrap!(encryptor.read(0, data.len() as u64).wait());
+        assert_eq!(Blob(&whole), Blob(&data));
+        let window = unwrap!(encryptor.read(1000, 500).wait());
+        assert_eq!(Blob(&window), Blob(&data[1000..1500]));
+    }
+
+    #[test]
+    fn write_at_reencrypts_only_affected_chunks() {
+        let mut rng = SeededRng::new();
+        let mut data = rng.gen_iter().take(8192).collect_vec();
+        let (data_map, storage) = encrypt(&data);
+        let original = data_map.get_sorted_chunks();
+
+        let mut encryptor = unwrap!(SelfEncryptor::new(storage, data_map));
+        let edit = vec![0xABu8; 32];
+        unwrap!(encryptor.write_at(1500, &edit).wait());
+        let (new_map, storage) = unwrap!(encryptor.close().wait());
+        let updated = new_map.get_sorted_chunks();
+
+        // The edit touches chunk 1; chunks 1, 2 and 3 re-encrypt (neighbour dependency) while the
+        // tail chunks keep their original hashes, so storage dedup still holds.
+        assert_ne!(updated[1].hash, original[1].hash);
+        for index in 4..original.len() {
+            assert_eq!(updated[index].hash, original[index].hash);
+        }
+
+        data[1500..1532].copy_from_slice(&edit);
+        let encryptor = unwrap!(SelfEncryptor::new(storage, new_map));
+        let whole = unwrap!(encryptor.read(0, data.len() as u64).wait());
+        assert_eq!(Blob(&whole), Blob(&data));
+    }
+
+    #[test]
+    fn write_at_empty_edit_is_a_no_op() {
+        let mut rng = SeededRng::new();
+        let data = rng.gen_iter().take(8192).collect_vec();
+        let (data_map, storage) = encrypt(&data);
+        let original = data_map.get_sorted_chunks();
+
+        // An empty edit must not underflow `end - 1` or re-emit any chunk.
+        let mut encryptor = unwrap!(SelfEncryptor::new(storage, data_map));
+        unwrap!(encryptor.write_at(0, &[]).wait());
+        let (new_map, storage) = unwrap!(encryptor.close().wait());
+        let updated = new_map.get_sorted_chunks();
+
+        assert_eq!(updated.len(), original.len());
+        for (new, old) in updated.iter().zip(original.iter()) {
+            assert_eq!(new.hash, old.hash);
+        }
+
+        let encryptor = unwrap!(SelfEncryptor::new(storage, new_map));
+        let whole = unwrap!(encryptor.read(0, data.len() as u64).wait());
+        assert_eq!(Blob(&whole), Blob(&data));
+    }
+
+    #[test]
+    fn truncate_drops_tail_chunks() {
+        let mut rng = SeededRng::new();
+        let data = rng.gen_iter().take(8192).collect_vec();
+        let (data_map, storage) = encrypt(&data);
+        let mut encryptor = unwrap!(SelfEncryptor::new(storage, data_map));
+        unwrap!(encryptor.truncate(5000).wait());
+        let (new_map, storage) = unwrap!(encryptor.close().wait());
+        let encryptor = unwrap!(SelfEncryptor::new(storage, new_map));
+        assert_eq!(encryptor.len(), 5000);
+        let whole = unwrap!(encryptor.read(0, 5000).wait());
+        assert_eq!(Blob(&whole), Blob(&data[..5000]));
+    }
+
+    #[test]
+    fn aead_detects_tampering() {
+        let mut rng = SeededRng::new();
+        let data = rng.gen_iter().take(4096).collect_vec();
+        let config = unwrap!(ChunkConfig::new(1024)).with_scheme(EncryptionScheme::Aead);
+
+        let (data_map, mut storage) = {
+            let mut encryptor = SelfEncryptor::with_config(SimpleStorage::new(), config);
+            unwrap!(encryptor.write(&data, 0).wait());
+            unwrap!(encryptor.close().wait())
+        };
+
+        // The scheme used must be recorded so reads take the AEAD path.
+        let (target, index) = match data_map {
+            DataMap::Chunks {
+                scheme: EncryptionScheme::Aead,
+                ref chunks,
+                ..
+            } => (chunks[1].hash.clone(), chunks[1].chunk_num),
+            _ => panic!("expected a chunked AEAD DataMap"),
+        };
+
+        // Substitute a chunk's ciphertext for garbage of the same length; the tag check must fail.
+        storage.corrupt(&target, vec![0u8; 1040]);
+        let encryptor = unwrap!(SelfEncryptor::new(storage, data_map));
+        match encryptor.read(0, data.len() as u64).wait() {
+            Err(SelfEncryptionError::IntegrityFailure { chunk_index }) => {
+                assert_eq!(chunk_index, index)
+            }
+            other => panic!("expected an integrity failure, got {:?}", other.map(|_| ())),
+        }
+    }
+
+    #[test]
+    fn write_at_grows_across_boundary() {
+        // Start with inline content, then grow it well past the chunking threshold.
+        let mut rng = SeededRng::new();
+        let tail = rng.gen_iter().take(4096).collect_vec();
+        let (data_map, storage) = {
+            let mut encryptor = unwrap!(SelfEncryptor::new(SimpleStorage::new(), DataMap::None));
+            unwrap!(encryptor.write(&[1u8; 100], 0).wait());
+            unwrap!(encryptor.close().wait())
+        };
+        match data_map {
+            DataMap::Content(_) => (),
+            _ => panic!("expected inline content for a small write"),
+        }
+
+        let mut encryptor = unwrap!(SelfEncryptor::new(storage, data_map));
+        unwrap!(encryptor.write_at(50, &tail).wait());
+        let (new_map, storage) = unwrap!(encryptor.close().wait());
+        match new_map {
+            DataMap::Chunks { .. } => (),
+            _ => panic!("expected a chunked DataMap after growth"),
+        }
+
+        let mut expected = vec![1u8; 100];
+        expected.resize(50 + tail.len(), 0);
+        expected[50..50 + tail.len()].copy_from_slice(&tail);
+        let encryptor = unwrap!(SelfEncryptor::new(storage, new_map));
+        let whole = unwrap!(encryptor.read(0, expected.len() as u64).wait());
+        assert_eq!(Blob(&whole), Blob(&expected));
+    }
+}
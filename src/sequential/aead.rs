@@ -0,0 +1,92 @@
+// Copyright 2018 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under The General Public License (GPL), version 3.
+// Unless required by applicable law or agreed to in writing, the SAFE Network Software distributed
+// under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied. Please review the Licences for the specific language governing
+// permissions and limitations relating to use of the SAFE Network Software.
+
+//! AEAD sealing for individual chunks.
+//!
+//! A per-chunk key and nonce are derived with HKDF-SHA256 from the chunk's key material (its own
+//! and its neighbours' plaintext hashes) salted by the chunk index, then the chunk is sealed with
+//! AES-256-GCM.  The index is bound into the nonce and the AAD so a chunk cannot be reordered, and
+//! a failed tag check on read surfaces as `SelfEncryptionError::IntegrityFailure`.
+
+use super::SelfEncryptionError;
+use ring::aead::{Aad, LessSafeKey, Nonce, UnboundKey, AES_256_GCM, NONCE_LEN};
+use ring::hkdf::{KeyType, Salt, HKDF_SHA256};
+
+// Length of the per-chunk key material derived from HKDF.
+const KEY_LEN: usize = 32;
+
+// Seals `plaintext` for the chunk at `chunk_index`.  `key_material` is the concatenation of the
+// chunk's and its predecessors' plaintext hashes, as used by the legacy path.  The returned buffer
+// is the ciphertext followed by the authentication tag.
+pub fn seal<E>(
+    chunk_index: u32,
+    key_material: &[u8],
+    plaintext: &[u8],
+) -> Result<Vec<u8>, SelfEncryptionError<E>> {
+    let (key, nonce) = derive(chunk_index, key_material);
+    let mut in_out = plaintext.to_vec();
+    key.seal_in_place_append_tag(nonce, aad(chunk_index), &mut in_out)
+        .map_err(|_| SelfEncryptionError::IntegrityFailure { chunk_index })?;
+    Ok(in_out)
+}
+
+// Verifies and decrypts a chunk sealed by `seal`.  Returns `IntegrityFailure` - carrying the
+// chunk index - when the tag check fails, rather than producing garbage plaintext.
+pub fn open<E>(
+    chunk_index: u32,
+    key_material: &[u8],
+    sealed: &[u8],
+) -> Result<Vec<u8>, SelfEncryptionError<E>> {
+    let (key, nonce) = derive(chunk_index, key_material);
+    let mut in_out = sealed.to_vec();
+    let plaintext = key
+        .open_in_place(nonce, aad(chunk_index), &mut in_out)
+        .map_err(|_| SelfEncryptionError::IntegrityFailure { chunk_index })?;
+    Ok(plaintext.to_vec())
+}
+
+// Builds the AEAD key and nonce from the chunk's key material, salted by its index so distinct
+// chunks never share key/nonce material.  The index is additionally folded into the nonce bytes.
+fn derive(chunk_index: u32, key_material: &[u8]) -> (LessSafeKey, Nonce) {
+    let index = chunk_index.to_le_bytes();
+    let prk = Salt::new(HKDF_SHA256, &index).extract(key_material);
+
+    let mut key_bytes = [0u8; KEY_LEN];
+    let _ = prk
+        .expand(&[b"self_encryption chunk key"], HkdfLen(KEY_LEN))
+        .and_then(|okm| okm.fill(&mut key_bytes));
+
+    let mut nonce_bytes = [0u8; NONCE_LEN];
+    let _ = prk
+        .expand(&[b"self_encryption chunk nonce"], HkdfLen(NONCE_LEN))
+        .and_then(|okm| okm.fill(&mut nonce_bytes));
+    for (slot, byte) in nonce_bytes[..index.len()].iter_mut().zip(index.iter()) {
+        *slot ^= *byte;
+    }
+
+    let unbound = UnboundKey::new(&AES_256_GCM, &key_bytes).expect("valid 256-bit key length");
+    (
+        LessSafeKey::new(unbound),
+        Nonce::assume_unique_for_key(nonce_bytes),
+    )
+}
+
+// The chunk index, bound into the AEAD additional authenticated data so a reordered chunk is
+// rejected.
+fn aad(chunk_index: u32) -> Aad<[u8; 4]> {
+    Aad::from(chunk_index.to_le_bytes())
+}
+
+// Adapter letting HKDF expand into an arbitrary output length.
+struct HkdfLen(usize);
+
+impl KeyType for HkdfLen {
+    fn len(&self) -> usize {
+        self.0
+    }
+}
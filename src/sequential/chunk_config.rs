@@ -0,0 +1,112 @@
+// Copyright 2018 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under The General Public License (GPL), version 3.
+// Unless required by applicable law or agreed to in writing, the SAFE Network Software distributed
+// under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied. Please review the Licences for the specific language governing
+// permissions and limitations relating to use of the SAFE Network Software.
+
+use super::MIN_CHUNK_SIZE;
+use std::error::Error;
+use std::fmt::{self, Display, Formatter};
+
+// Smallest chunk size a caller may request.  Matches the lower bound used by chunked AEAD
+// implementations and keeps the per-chunk overhead meaningful relative to the payload.
+pub const MIN_CHUNK_CONFIG_SIZE: u64 = 64;
+
+// Largest chunk size a caller may request.
+pub const MAX_CHUNK_CONFIG_SIZE: u64 = 4 * 1024 * 1024;
+
+// The per-chunk encryption scheme a `ChunkConfig` selects.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Serialize, Deserialize)]
+pub enum EncryptionScheme {
+    // The historic XOR-pad plus symmetric cipher.  Tampering is only detected late, by the outer
+    // self-verification.
+    Legacy,
+    // Each chunk is sealed with an AEAD cipher under a per-chunk key and nonce derived from the
+    // chunk's own and its predecessors' plaintext hashes and index (see the `aead` module), so a
+    // corrupted or substituted chunk is rejected on read with
+    // `SelfEncryptionError::IntegrityFailure`.
+    Aead,
+}
+
+// A caller-chosen chunk size, validated against the supported `[MIN, MAX]` range.  The
+// small/medium/large boundaries and the `DataMap::Chunks` threshold are derived from the value
+// carried here rather than from the `MIN_CHUNK_SIZE` constant, so a consumer can trade chunk
+// count against per-chunk overhead for their storage backend.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Serialize, Deserialize)]
+pub struct ChunkConfig {
+    chunk_size: u64,
+    scheme: EncryptionScheme,
+}
+
+// Returned by `ChunkConfig::new` when the requested chunk size is outside the supported range.
+// Chunk-size validation is independent of the storage backend, so this error carries no `E`.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct ChunkConfigError {
+    // The rejected chunk size.
+    chunk_size: u64,
+}
+
+impl Display for ChunkConfigError {
+    fn fmt(&self, formatter: &mut Formatter) -> fmt::Result {
+        write!(
+            formatter,
+            "chunk size {} is outside the supported range [{}, {}]",
+            self.chunk_size, MIN_CHUNK_CONFIG_SIZE, MAX_CHUNK_CONFIG_SIZE
+        )
+    }
+}
+
+impl Error for ChunkConfigError {}
+
+impl ChunkConfig {
+    // Constructs a config from `chunk_size`, returning an error if it falls outside the supported
+    // range.
+    pub fn new(chunk_size: u64) -> Result<ChunkConfig, ChunkConfigError> {
+        if chunk_size < MIN_CHUNK_CONFIG_SIZE || chunk_size > MAX_CHUNK_CONFIG_SIZE {
+            return Err(ChunkConfigError { chunk_size });
+        }
+        Ok(ChunkConfig {
+            chunk_size,
+            scheme: EncryptionScheme::Legacy,
+        })
+    }
+
+    // Returns a copy of this config using the given per-chunk encryption scheme.
+    pub fn with_scheme(mut self, scheme: EncryptionScheme) -> ChunkConfig {
+        self.scheme = scheme;
+        self
+    }
+
+    // The configured chunk size.
+    pub fn chunk_size(&self) -> u64 {
+        self.chunk_size
+    }
+
+    // The configured per-chunk encryption scheme.
+    pub fn scheme(&self) -> EncryptionScheme {
+        self.scheme
+    }
+
+    // Largest payload a `SmallEncryptor` may hold before it must be promoted - i.e. the point at
+    // which three chunks become possible and the data collapses out of `DataMap::Content`.
+    pub fn small_max(&self) -> u64 {
+        (3 * self.chunk_size) - 1
+    }
+
+    // Largest payload a `MediumEncryptor` may hold before promotion to a `LargeEncryptor`.
+    pub fn medium_max(&self) -> u64 {
+        3 * self.chunk_size
+    }
+}
+
+impl Default for ChunkConfig {
+    // The default preserves the historic granularity so existing consumers are unaffected.
+    fn default() -> ChunkConfig {
+        ChunkConfig {
+            chunk_size: MIN_CHUNK_SIZE as u64,
+            scheme: EncryptionScheme::Legacy,
+        }
+    }
+}
@@ -0,0 +1,241 @@
+// Copyright 2018 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under The General Public License (GPL), version 3.
+// Unless required by applicable law or agreed to in writing, the SAFE Network Software distributed
+// under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied. Please review the Licences for the specific language governing
+// permissions and limitations relating to use of the SAFE Network Software.
+
+use super::snapshot::{EncryptorKind, EncryptorSnapshot};
+use super::{ChunkConfig, SelfEncryptionError, Storage};
+use data_map::{ChunkDetails, DataMap};
+use encryption;
+use futures::{future, Future};
+use util::{BoxFuture, FutureExt};
+
+// The chunk-emitting engine shared by `MediumEncryptor` and `LargeEncryptor`.  Bytes are buffered
+// until a full `chunk_size` is available, at which point a chunk is encrypted and written to
+// storage immediately; because a chunk's key depends only on earlier chunks' hashes, nothing need
+// be held back once a chunk boundary is reached, so the engine streams in constant memory and its
+// in-flight state snapshots cleanly.
+pub struct ChunkStream<S> {
+    pub storage: S,
+    config: ChunkConfig,
+    chunks: Vec<ChunkDetails>,
+    tail: Vec<u8>,
+    offset: u64,
+}
+
+impl<S> ChunkStream<S>
+where
+    S: Storage + 'static,
+{
+    // Creates an engine seeded with `buffer` already-unflushed bytes (used when promoting from a
+    // smaller encryptor).
+    pub fn new(storage: S, buffer: Vec<u8>, config: ChunkConfig) -> ChunkStream<S> {
+        ChunkStream {
+            storage,
+            config,
+            chunks: vec![],
+            tail: buffer,
+            offset: 0,
+        }
+    }
+
+    // Rebuilds an engine from a snapshot, reusing the already-flushed chunks so none are re-emitted.
+    pub fn resume(storage: S, snapshot: EncryptorSnapshot) -> ChunkStream<S> {
+        ChunkStream {
+            storage,
+            config: snapshot.config,
+            chunks: snapshot.chunks,
+            tail: snapshot.tail,
+            offset: snapshot.offset,
+        }
+    }
+
+    // Captures the in-flight state for a later `resume`.  `kind` is supplied by the owning
+    // encryptor so the dispatching `Encryptor::resume` can rebuild the right variant.
+    pub fn snapshot(&self, kind: EncryptorKind) -> EncryptorSnapshot {
+        EncryptorSnapshot {
+            config: self.config,
+            kind,
+            offset: self.offset,
+            tail: self.tail.clone(),
+            chunks: self.chunks.clone(),
+        }
+    }
+
+    // The active chunk configuration.
+    pub fn config(&self) -> ChunkConfig {
+        self.config
+    }
+
+    // Recovers the storage without flushing the tail, for pausing an upload after `snapshot`.
+    pub fn into_storage(self) -> S {
+        self.storage
+    }
+
+    // Total number of source bytes consumed so far, including the unflushed tail.
+    pub fn len(&self) -> u64 {
+        self.offset + self.tail.len() as u64
+    }
+
+    // Appends `data`, flushing as many full chunks as become available.
+    pub fn write(mut self, data: &[u8]) -> BoxFuture<Self, SelfEncryptionError<S::Error>> {
+        self.tail.extend_from_slice(data);
+        let chunk_size = self.config.chunk_size() as usize;
+        drain_full_chunks(self, chunk_size)
+    }
+
+    // Finalises the engine, flushing the tail as the last chunk and returning the `DataMap`.
+    pub fn close(self) -> BoxFuture<(DataMap, S), SelfEncryptionError<S::Error>> {
+        let tail = self.tail.clone();
+        let config = self.config;
+        // Only emit the final chunk when it carries data, or when nothing has been
+        // emitted yet so an empty file still round-trips. Without this guard an input
+        // whose length is an exact multiple of `chunk_size` would append a spurious
+        // zero-length trailing chunk, diverging from `SelfEncryptor::encode_all`.
+        let finalise = move |mut stream: Self| {
+            stream.tail.clear();
+            let data_map = DataMap::Chunks {
+                chunks: stream.chunks.clone(),
+                chunk_size: config.chunk_size(),
+                scheme: config.scheme(),
+            };
+            (data_map, stream.storage)
+        };
+        if tail.is_empty() && !self.chunks.is_empty() {
+            future::ok(finalise(self)).into_box()
+        } else {
+            self.emit(tail)
+                .map(finalise)
+                .into_box()
+        }
+    }
+
+    // Encrypts and stores `chunk` as the next chunk, recording its details.
+    fn emit(mut self, chunk: Vec<u8>) -> BoxFuture<Self, SelfEncryptionError<S::Error>> {
+        let chunk_index = self.chunks.len() as u32;
+        let (pred1, pred2) = self.predecessor_hashes();
+        let result = encryption::encode(self.config.scheme(), chunk_index, &chunk, &pred1, &pred2);
+        let (ciphertext, name) = match result {
+            Ok(pair) => pair,
+            Err(error) => return future::err(error).into_box(),
+        };
+        let details = ChunkDetails {
+            chunk_num: chunk_index,
+            hash: name.clone(),
+            pre_hash: encryption::pre_hash(&chunk),
+            source_size: chunk.len() as u64,
+        };
+        self.offset += chunk.len() as u64;
+        self.chunks.push(details);
+        self.storage
+            .put(name, ciphertext)
+            .map_err(SelfEncryptionError::Storage)
+            .map(move |()| self)
+            .into_box()
+    }
+
+    // The plaintext hashes of the two preceding chunks, or empty slices when they do not exist.
+    fn predecessor_hashes(&self) -> (Vec<u8>, Vec<u8>) {
+        let len = self.chunks.len();
+        let pred1 = if len >= 1 {
+            self.chunks[len - 1].pre_hash.clone()
+        } else {
+            vec![]
+        };
+        let pred2 = if len >= 2 {
+            self.chunks[len - 2].pre_hash.clone()
+        } else {
+            vec![]
+        };
+        (pred1, pred2)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::{
+        ChunkConfig, Encryptor, LargeEncryptor, MediumEncryptor, SmallEncryptor,
+    };
+    use data_map::DataMap;
+    use futures::Future;
+    use itertools::Itertools;
+    use maidsafe_utilities::SeededRng;
+    use rand::Rng;
+    use test_helpers::SimpleStorage;
+
+    // Drives a large encryptor over `data` in a single uninterrupted run.
+    fn uninterrupted(data: &[u8]) -> (DataMap, usize) {
+        let config = unwrap!(ChunkConfig::new(1024));
+        let small = unwrap!(SmallEncryptor::new(SimpleStorage::new(), vec![], config).wait());
+        let medium = unwrap!(MediumEncryptor::new(small).wait());
+        let mut large = unwrap!(LargeEncryptor::new(medium).wait());
+        large = unwrap!(large.write(data).wait());
+        let (data_map, storage) = unwrap!(large.close().wait());
+        (data_map, storage.put_count)
+    }
+
+    #[test]
+    fn resume_matches_uninterrupted_run() {
+        let mut rng = SeededRng::new();
+        let data = rng.gen_iter().take(10_000).collect_vec();
+        let (expected_map, expected_puts) = uninterrupted(&data);
+
+        // Write the first half, snapshot, then resume onto the same storage and finish.
+        let config = unwrap!(ChunkConfig::new(1024));
+        let small = unwrap!(SmallEncryptor::new(SimpleStorage::new(), vec![], config).wait());
+        let medium = unwrap!(MediumEncryptor::new(small).wait());
+        let mut large = unwrap!(LargeEncryptor::new(medium).wait());
+        large = unwrap!(large.write(&data[..5000]).wait());
+        let snapshot = large.snapshot();
+        let storage = large.into_storage();
+
+        let resumed = unwrap!(LargeEncryptor::resume(storage, snapshot).wait());
+        let resumed = unwrap!(resumed.write(&data[5000..]).wait());
+        let (resumed_map, storage) = unwrap!(resumed.close().wait());
+
+        // Byte-identical map, and no chunk was stored twice across the interruption.
+        assert_eq!(resumed_map, expected_map);
+        assert_eq!(storage.put_count, expected_puts);
+    }
+
+    #[test]
+    fn dispatching_resume_preserves_small_content() {
+        // A sub-threshold small encryptor must resume as a `SmallEncryptor` and still collapse to
+        // `DataMap::Content`, not finalise as `DataMap::Chunks`.
+        let config = unwrap!(ChunkConfig::new(1024));
+        let small = unwrap!(SmallEncryptor::new(SimpleStorage::new(), vec![], config).wait());
+        let small = unwrap!(small.write(&[7u8; 100]).wait());
+        let snapshot = small.snapshot();
+        let storage = small.storage;
+
+        let resumed = unwrap!(Encryptor::resume(storage, snapshot).wait());
+        let resumed = unwrap!(resumed.write(&[7u8; 50]).wait());
+        let (data_map, _) = unwrap!(resumed.close().wait());
+        match data_map {
+            DataMap::Content(ref content) => assert_eq!(content.len(), 150),
+            _ => panic!("expected inline content for a resumed small encryptor"),
+        }
+    }
+}
+
+// Emits every remaining full chunk in the tail.
+fn drain_full_chunks<S>(
+    mut stream: ChunkStream<S>,
+    chunk_size: usize,
+) -> BoxFuture<ChunkStream<S>, SelfEncryptionError<S::Error>>
+where
+    S: Storage + 'static,
+{
+    if stream.tail.len() >= chunk_size {
+        let chunk: Vec<u8> = stream.tail.drain(..chunk_size).collect();
+        stream
+            .emit(chunk)
+            .and_then(move |stream| drain_full_chunks(stream, chunk_size))
+            .into_box()
+    } else {
+        future::ok(stream).into_box()
+    }
+}
@@ -0,0 +1,140 @@
+// Copyright 2018 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under The General Public License (GPL), version 3.
+// Unless required by applicable law or agreed to in writing, the SAFE Network Software distributed
+// under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied. Please review the Licences for the specific language governing
+// permissions and limitations relating to use of the SAFE Network Software.
+
+use super::Storage;
+use data_map::DataMap;
+use futures::Future;
+use self_encryptor::SelfEncryptor;
+use std::io::{self, Read, Seek, SeekFrom};
+
+// A synchronous `std::io::Read` + `Seek` adapter over a `SelfEncryptor`.  Each read fetches and
+// decrypts only the chunks spanning the current window via the underlying encryptor, so arbitrary
+// `Read` sinks can be fed without materialising the whole blob.  Fetched chunks are not cached:
+// reading the same window again fetches and decrypts its chunks again.
+pub struct DecryptReader<S> {
+    encryptor: SelfEncryptor<S>,
+    position: u64,
+}
+
+impl<S> DecryptReader<S>
+where
+    S: Storage + 'static,
+{
+    // Creates a reader over the data described by `data_map`, positioned at the start.
+    pub fn new(storage: S, data_map: DataMap) -> io::Result<DecryptReader<S>> {
+        let encryptor = SelfEncryptor::new(storage, data_map).map_err(into_io)?;
+        Ok(DecryptReader {
+            encryptor,
+            position: 0,
+        })
+    }
+
+    // Total length of the decrypted data.
+    pub fn len(&self) -> u64 {
+        self.encryptor.len()
+    }
+
+    // Returns `true` if the decrypted data is empty.
+    pub fn is_empty(&self) -> bool {
+        self.encryptor.len() == 0
+    }
+}
+
+impl<S> Read for DecryptReader<S>
+where
+    S: Storage + 'static,
+{
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let remaining = self.encryptor.len().saturating_sub(self.position);
+        if remaining == 0 || buf.is_empty() {
+            return Ok(0);
+        }
+        let wanted = (buf.len() as u64).min(remaining);
+        let fetched = self
+            .encryptor
+            .read(self.position, wanted)
+            .wait()
+            .map_err(into_io)?;
+        buf[..fetched.len()].copy_from_slice(&fetched);
+        self.position += fetched.len() as u64;
+        Ok(fetched.len())
+    }
+}
+
+impl<S> Seek for DecryptReader<S>
+where
+    S: Storage + 'static,
+{
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        let len = self.encryptor.len();
+        let target = match pos {
+            SeekFrom::Start(offset) => offset as i64,
+            SeekFrom::End(offset) => len as i64 + offset,
+            SeekFrom::Current(offset) => self.position as i64 + offset,
+        };
+        if target < 0 {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "seek to a negative position",
+            ));
+        }
+        self.position = target as u64;
+        Ok(self.position)
+    }
+}
+
+// Flattens a `SelfEncryptionError` into an `io::Error` so the adapter satisfies the `Read`
+// contract without exposing the crate's error type through `std::io`.
+fn into_io<E: ::std::fmt::Display>(error: E) -> io::Error {
+    io::Error::new(io::ErrorKind::Other, error.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::encrypt_writer::EncryptWriter;
+    use super::super::ChunkConfig;
+    use super::DecryptReader;
+    use itertools::Itertools;
+    use maidsafe_utilities::SeededRng;
+    use rand::Rng;
+    use std::io::{Read, Seek, SeekFrom, Write};
+    use test_helpers::{Blob, SimpleStorage};
+
+    // Encrypts `data` and returns a reader over it.
+    fn reader_over(data: &[u8]) -> DecryptReader<SimpleStorage> {
+        let config = unwrap!(ChunkConfig::new(1024));
+        let mut writer = unwrap!(EncryptWriter::new(SimpleStorage::new(), config));
+        unwrap!(writer.write_all(data));
+        let (data_map, storage) = unwrap!(writer.finish());
+        unwrap!(DecryptReader::new(storage, data_map))
+    }
+
+    #[test]
+    fn seek_then_read_returns_the_right_window() {
+        let mut rng = SeededRng::new();
+        let data = rng.gen_iter().take(10_000).collect_vec();
+        let mut reader = reader_over(&data);
+
+        assert_eq!(unwrap!(reader.seek(SeekFrom::Start(4000))), 4000);
+        let mut window = vec![0u8; 2000];
+        unwrap!(reader.read_exact(&mut window));
+        assert_eq!(Blob(&window), Blob(&data[4000..6000]));
+
+        // `SeekFrom::End` lands relative to the decrypted length.
+        assert_eq!(unwrap!(reader.seek(SeekFrom::End(-1000))), 9000);
+        let mut tail = vec![];
+        let _ = unwrap!(reader.read_to_end(&mut tail));
+        assert_eq!(Blob(&tail), Blob(&data[9000..]));
+    }
+
+    #[test]
+    fn seek_before_start_is_rejected() {
+        let mut reader = reader_over(&[0u8; 256][..]);
+        assert!(reader.seek(SeekFrom::Current(-1)).is_err());
+    }
+}
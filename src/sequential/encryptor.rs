@@ -0,0 +1,77 @@
+// Copyright 2018 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under The General Public License (GPL), version 3.
+// Unless required by applicable law or agreed to in writing, the SAFE Network Software distributed
+// under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied. Please review the Licences for the specific language governing
+// permissions and limitations relating to use of the SAFE Network Software.
+
+use super::large_encryptor::LargeEncryptor;
+use super::medium_encryptor::MediumEncryptor;
+use super::small_encryptor::SmallEncryptor;
+use super::snapshot::{EncryptorKind, EncryptorSnapshot};
+use super::{SelfEncryptionError, Storage};
+use data_map::DataMap;
+use futures::Future;
+use util::{BoxFuture, FutureExt};
+
+// The concrete encryptor backing a resumed upload.  `resume` rebuilds the variant recorded in the
+// snapshot so the finalised `DataMap` is byte-identical to one from an uninterrupted run - in
+// particular a `Small` snapshot is rebuilt as a `SmallEncryptor` and so still collapses to
+// `DataMap::Content`, where treating it as medium/large would have produced `DataMap::Chunks`.
+pub enum Encryptor<S> {
+    Small(SmallEncryptor<S>),
+    Medium(MediumEncryptor<S>),
+    Large(LargeEncryptor<S>),
+}
+
+impl<S> Encryptor<S>
+where
+    S: Storage + 'static,
+{
+    // Rebuilds the correct concrete encryptor from a snapshot, reusing the already-flushed chunks
+    // so none are re-emitted on resume.
+    pub fn resume(
+        storage: S,
+        snapshot: EncryptorSnapshot,
+    ) -> BoxFuture<Encryptor<S>, SelfEncryptionError<S::Error>> {
+        match snapshot.kind {
+            EncryptorKind::Small => SmallEncryptor::resume(storage, snapshot)
+                .map(Encryptor::Small)
+                .into_box(),
+            EncryptorKind::Medium => MediumEncryptor::resume(storage, snapshot)
+                .map(Encryptor::Medium)
+                .into_box(),
+            EncryptorKind::Large => LargeEncryptor::resume(storage, snapshot)
+                .map(Encryptor::Large)
+                .into_box(),
+        }
+    }
+
+    // Captures the in-flight state for a later `resume`.
+    pub fn snapshot(&self) -> EncryptorSnapshot {
+        match *self {
+            Encryptor::Small(ref e) => e.snapshot(),
+            Encryptor::Medium(ref e) => e.snapshot(),
+            Encryptor::Large(ref e) => e.snapshot(),
+        }
+    }
+
+    // Appends `data`, flushing full chunks as they become available.
+    pub fn write(self, data: &[u8]) -> BoxFuture<Encryptor<S>, SelfEncryptionError<S::Error>> {
+        match self {
+            Encryptor::Small(e) => e.write(data).map(Encryptor::Small).into_box(),
+            Encryptor::Medium(e) => e.write(data).map(Encryptor::Medium).into_box(),
+            Encryptor::Large(e) => e.write(data).map(Encryptor::Large).into_box(),
+        }
+    }
+
+    // Finalises the encryptor, returning the completed `DataMap` and storage.
+    pub fn close(self) -> BoxFuture<(DataMap, S), SelfEncryptionError<S::Error>> {
+        match self {
+            Encryptor::Small(e) => e.close(),
+            Encryptor::Medium(e) => e.close(),
+            Encryptor::Large(e) => e.close(),
+        }
+    }
+}
@@ -0,0 +1,82 @@
+// Copyright 2018 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under The General Public License (GPL), version 3.
+// Unless required by applicable law or agreed to in writing, the SAFE Network Software distributed
+// under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied. Please review the Licences for the specific language governing
+// permissions and limitations relating to use of the SAFE Network Software.
+
+use super::chunk_stream::ChunkStream;
+use super::medium_encryptor::MediumEncryptor;
+use super::snapshot::EncryptorKind;
+use super::{ChunkConfig, EncryptorSnapshot, SelfEncryptionError, Storage};
+use data_map::DataMap;
+use futures::{future, Future};
+use util::{BoxFuture, FutureExt};
+
+// An encryptor for data streamed in arbitrarily large amounts.  Like `MediumEncryptor` it is a
+// wrapper over the shared `ChunkStream`, kept as a distinct type so the chain's promotion
+// boundaries read clearly at the call site.
+pub struct LargeEncryptor<S> {
+    stream: ChunkStream<S>,
+    config: ChunkConfig,
+}
+
+impl<S> LargeEncryptor<S>
+where
+    S: Storage + 'static,
+{
+    // Promotes a `MediumEncryptor`, reusing its already-flushed chunks and unflushed tail.
+    pub fn new(medium: MediumEncryptor<S>) -> BoxFuture<LargeEncryptor<S>, SelfEncryptionError<S::Error>> {
+        let stream = medium.into_stream();
+        let config = stream.config();
+        ::futures::future::ok(LargeEncryptor { stream, config }).into_box()
+    }
+
+    // Rebuilds an encryptor from a snapshot, reusing the already-flushed chunks so none are
+    // re-emitted on resume.  The `DataMap` produced by a resumed run is byte-identical to one from
+    // an uninterrupted run over the same input.
+    pub fn resume(
+        storage: S,
+        snapshot: EncryptorSnapshot,
+    ) -> BoxFuture<LargeEncryptor<S>, SelfEncryptionError<S::Error>> {
+        let config = snapshot.config;
+        let stream = ChunkStream::resume(storage, snapshot);
+        future::ok(LargeEncryptor { stream, config }).into_box()
+    }
+
+    // Captures the in-flight state - emitted chunk details, unflushed tail and byte offset - for a
+    // later `resume`.
+    pub fn snapshot(&self) -> EncryptorSnapshot {
+        self.stream.snapshot(EncryptorKind::Large)
+    }
+
+    // Recovers the storage without flushing the tail, for pausing an upload after `snapshot`.
+    pub fn into_storage(self) -> S {
+        self.stream.into_storage()
+    }
+
+    // Appends `data`, flushing full chunks as they become available.
+    pub fn write(mut self, data: &[u8]) -> BoxFuture<Self, SelfEncryptionError<S::Error>> {
+        let config = self.config;
+        self.stream
+            .write(data)
+            .map(move |stream| LargeEncryptor { stream, config })
+            .into_box()
+    }
+
+    // Finalises the encryptor, returning the completed `DataMap` and storage.
+    pub fn close(self) -> BoxFuture<(DataMap, S), SelfEncryptionError<S::Error>> {
+        self.stream.close()
+    }
+
+    // The number of source bytes consumed so far.
+    pub fn len(&self) -> u64 {
+        self.stream.len()
+    }
+
+    // Returns `true` if nothing has been written.
+    pub fn is_empty(&self) -> bool {
+        self.stream.len() == 0
+    }
+}
@@ -0,0 +1,81 @@
+// Copyright 2018 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under The General Public License (GPL), version 3.
+// Unless required by applicable law or agreed to in writing, the SAFE Network Software distributed
+// under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied. Please review the Licences for the specific language governing
+// permissions and limitations relating to use of the SAFE Network Software.
+
+use super::chunk_stream::ChunkStream;
+use super::small_encryptor::SmallEncryptor;
+use super::snapshot::EncryptorKind;
+use super::{ChunkConfig, EncryptorSnapshot, SelfEncryptionError, Storage};
+use data_map::DataMap;
+use futures::{future, Future};
+use util::{BoxFuture, FutureExt};
+
+// An encryptor for data large enough to split into chunks but still modest in size.  It is a thin
+// wrapper over the shared `ChunkStream`; `LargeEncryptor` differs only in how far it is driven.
+pub struct MediumEncryptor<S> {
+    stream: ChunkStream<S>,
+    config: ChunkConfig,
+}
+
+impl<S> MediumEncryptor<S>
+where
+    S: Storage + 'static,
+{
+    // Promotes a `SmallEncryptor`, carrying its buffered bytes and config across without
+    // re-emitting anything.
+    pub fn new(small: SmallEncryptor<S>) -> BoxFuture<MediumEncryptor<S>, SelfEncryptionError<S::Error>> {
+        let config = small.config;
+        let stream = ChunkStream::new(small.storage, small.buffer, config);
+        ::futures::future::ok(MediumEncryptor { stream, config }).into_box()
+    }
+
+    // Rebuilds an encryptor from a snapshot, reusing the already-flushed chunks so none are
+    // re-emitted on resume.
+    pub fn resume(
+        storage: S,
+        snapshot: EncryptorSnapshot,
+    ) -> BoxFuture<MediumEncryptor<S>, SelfEncryptionError<S::Error>> {
+        let config = snapshot.config;
+        let stream = ChunkStream::resume(storage, snapshot);
+        future::ok(MediumEncryptor { stream, config }).into_box()
+    }
+
+    // Captures the in-flight state - emitted chunk details, unflushed tail and byte offset - for a
+    // later `resume`.
+    pub fn snapshot(&self) -> EncryptorSnapshot {
+        self.stream.snapshot(EncryptorKind::Medium)
+    }
+
+    // Appends `data`, flushing full chunks as they become available.
+    pub fn write(mut self, data: &[u8]) -> BoxFuture<Self, SelfEncryptionError<S::Error>> {
+        let config = self.config;
+        self.stream
+            .write(data)
+            .map(move |stream| MediumEncryptor { stream, config })
+            .into_box()
+    }
+
+    // Finalises the encryptor, returning the completed `DataMap` and storage.
+    pub fn close(self) -> BoxFuture<(DataMap, S), SelfEncryptionError<S::Error>> {
+        self.stream.close()
+    }
+
+    // Hands the underlying stream to a `LargeEncryptor` on promotion.
+    pub(crate) fn into_stream(self) -> ChunkStream<S> {
+        self.stream
+    }
+
+    // The number of source bytes consumed so far.
+    pub fn len(&self) -> u64 {
+        self.stream.len()
+    }
+
+    // Returns `true` if nothing has been written.
+    pub fn is_empty(&self) -> bool {
+        self.stream.len() == 0
+    }
+}
@@ -0,0 +1,31 @@
+// Copyright 2018 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under The General Public License (GPL), version 3.
+// Unless required by applicable law or agreed to in writing, the SAFE Network Software distributed
+// under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied. Please review the Licences for the specific language governing
+// permissions and limitations relating to use of the SAFE Network Software.
+
+//! The sequential encryptor chain: a `SmallEncryptor` which promotes to a `MediumEncryptor` and
+//! then a `LargeEncryptor` as more data is written, plus the streaming adapters built over it.
+
+pub use self::chunk_config::{ChunkConfig, ChunkConfigError, EncryptionScheme};
+pub use self::decrypt_reader::DecryptReader;
+pub use self::encrypt_writer::EncryptWriter;
+pub use self::encryptor::Encryptor;
+pub use self::large_encryptor::LargeEncryptor;
+pub use self::medium_encryptor::MediumEncryptor;
+pub use self::small_encryptor::SmallEncryptor;
+pub use self::snapshot::{EncryptorKind, EncryptorSnapshot};
+pub use {SelfEncryptionError, Storage, MIN_CHUNK_SIZE};
+
+pub mod aead;
+mod chunk_config;
+mod chunk_stream;
+mod decrypt_reader;
+mod encrypt_writer;
+mod encryptor;
+pub mod large_encryptor;
+pub mod medium_encryptor;
+pub mod small_encryptor;
+mod snapshot;
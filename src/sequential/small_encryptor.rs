@@ -6,11 +6,15 @@
 // KIND, either express or implied. Please review the Licences for the specific language governing
 // permissions and limitations relating to use of the SAFE Network Software.
 
-use super::{SelfEncryptionError, Storage, MIN_CHUNK_SIZE};
+use super::snapshot::{EncryptorKind, EncryptorSnapshot};
+use super::{ChunkConfig, SelfEncryptionError, Storage, MIN_CHUNK_SIZE};
 use data_map::DataMap;
 use futures::future;
 use util::{BoxFuture, FutureExt};
 
+// Retained for callers which still reason about the default granularity.  Boundaries are now
+// computed per-encryptor from the active `ChunkConfig` (see `SmallEncryptor::max`) so that a
+// caller-chosen chunk size moves the small/medium/large thresholds with it.
 pub const MAX: u64 = (3 * MIN_CHUNK_SIZE as u64) - 1;
 
 // An encryptor for data which is too small to split into three chunks.  This will never make any
@@ -19,6 +23,7 @@ pub const MAX: u64 = (3 * MIN_CHUNK_SIZE as u64) - 1;
 pub struct SmallEncryptor<S> {
     pub storage: S,
     pub buffer: Vec<u8>,
+    pub config: ChunkConfig,
 }
 
 impl<S> SmallEncryptor<S>
@@ -29,18 +34,20 @@ where
     pub fn new(
         storage: S,
         data: Vec<u8>,
+        config: ChunkConfig,
     ) -> BoxFuture<SmallEncryptor<S>, SelfEncryptionError<S::Error>> {
-        debug_assert!(data.len() as u64 <= MAX);
+        debug_assert!(data.len() as u64 <= config.small_max());
         future::ok(SmallEncryptor {
             storage,
             buffer: data,
+            config,
         }).into_box()
     }
 
     // Simply appends to internal buffer assuming the size limit is not exceeded.  No chunks are
     // generated by this call.
     pub fn write(mut self, data: &[u8]) -> BoxFuture<Self, SelfEncryptionError<S::Error>> {
-        debug_assert!(data.len() as u64 + self.len() <= MAX);
+        debug_assert!(data.len() as u64 + self.len() <= self.max());
         self.buffer.extend_from_slice(data);
         future::ok(self).into_box()
     }
@@ -51,6 +58,35 @@ where
         future::ok((DataMap::Content(self.buffer), self.storage)).into_box()
     }
 
+    // Rebuilds a `SmallEncryptor` from a snapshot.  A small encryptor has emitted no chunks, so
+    // the snapshot's `tail` is exactly the buffered payload and no storage is re-read.
+    pub fn resume(
+        storage: S,
+        snapshot: EncryptorSnapshot,
+    ) -> BoxFuture<SmallEncryptor<S>, SelfEncryptionError<S::Error>> {
+        debug_assert!(snapshot.chunks.is_empty());
+        SmallEncryptor::new(storage, snapshot.tail, snapshot.config)
+    }
+
+    // Captures the in-flight state so an interrupted upload can be resumed without re-emitting any
+    // chunk.  A small encryptor has written nothing to storage, so `chunks` is empty, `offset` is
+    // zero and `tail` is the whole buffer.
+    pub fn snapshot(&self) -> EncryptorSnapshot {
+        EncryptorSnapshot {
+            config: self.config,
+            kind: EncryptorKind::Small,
+            offset: 0,
+            tail: self.buffer.clone(),
+            chunks: vec![],
+        }
+    }
+
+    // Largest payload this encryptor may hold before it must be promoted, derived from the active
+    // `ChunkConfig` rather than the `MIN_CHUNK_SIZE` constant.
+    pub fn max(&self) -> u64 {
+        self.config.small_max()
+    }
+
     pub fn len(&self) -> u64 {
         self.buffer.len() as u64
     }
@@ -77,7 +113,8 @@ mod tests {
     fn basic_write_and_close(data: &[u8]) {
         let (data_map, storage) = {
             let storage = SimpleStorage::new();
-            let mut encryptor = unwrap!(SmallEncryptor::new(storage, vec![]).wait());
+            let mut encryptor =
+                unwrap!(SmallEncryptor::new(storage, vec![], ChunkConfig::default()).wait());
             assert_eq!(encryptor.len(), 0);
             assert!(encryptor.is_empty());
             encryptor = unwrap!(encryptor.write(data).wait());
@@ -106,8 +143,11 @@ mod tests {
         for data in data_pieces {
             let (data_map, storage) = {
                 let storage = SimpleStorage::new();
-                let mut encryptor =
-                    unwrap!(SmallEncryptor::new(storage, existing_data.clone()).wait());
+                let mut encryptor = unwrap!(SmallEncryptor::new(
+                    storage,
+                    existing_data.clone(),
+                    ChunkConfig::default()
+                ).wait());
                 encryptor = unwrap!(encryptor.write(data).wait());
                 existing_data.extend_from_slice(data);
                 assert_eq!(encryptor.len(), existing_data.len() as u64);
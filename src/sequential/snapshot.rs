@@ -0,0 +1,41 @@
+// Copyright 2018 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under The General Public License (GPL), version 3.
+// Unless required by applicable law or agreed to in writing, the SAFE Network Software distributed
+// under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied. Please review the Licences for the specific language governing
+// permissions and limitations relating to use of the SAFE Network Software.
+
+use super::ChunkConfig;
+use data_map::ChunkDetails;
+
+// Identifies which concrete encryptor a snapshot was taken from, so `Encryptor::resume` can
+// rebuild the same variant rather than guessing from the buffer sizes.  This matters because a
+// `SmallEncryptor` finalises to `DataMap::Content` whereas the medium/large encryptors finalise to
+// `DataMap::Chunks`; resuming a small snapshot as a large encryptor would silently change the
+// resulting `DataMap`.
+#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
+pub enum EncryptorKind {
+    Small,
+    Medium,
+    Large,
+}
+
+// A compact, serialisable snapshot of an in-flight encryptor chain, used to resume a large upload
+// that was interrupted without re-emitting chunks already written to storage.
+//
+// `chunks` holds the details of every chunk already flushed; `tail` is the unflushed buffer that
+// had not yet reached a chunk boundary; `offset` is the number of source bytes already flushed
+// into `chunks` (so the bytes consumed so far are `offset` plus the length of `tail`);
+// `kind` records which concrete encryptor captured the snapshot.  Together with `config` these are
+// sufficient to rebuild the exact concrete encryptor (small, medium or large) via
+// `Encryptor::resume`, so the `DataMap` produced by the resumed run is byte-identical to one
+// produced by an uninterrupted run.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct EncryptorSnapshot {
+    pub config: ChunkConfig,
+    pub kind: EncryptorKind,
+    pub offset: u64,
+    pub tail: Vec<u8>,
+    pub chunks: Vec<ChunkDetails>,
+}
@@ -0,0 +1,79 @@
+// Copyright 2018 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under The General Public License (GPL), version 3.
+// Unless required by applicable law or agreed to in writing, the SAFE Network Software distributed
+// under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied. Please review the Licences for the specific language governing
+// permissions and limitations relating to use of the SAFE Network Software.
+
+use futures::future;
+use std::collections::HashMap;
+use std::fmt;
+use util::{BoxFuture, FutureExt};
+use {Storage, StorageError};
+
+// Error returned by `SimpleStorage` when a requested chunk is missing.
+#[derive(Debug)]
+pub struct SimpleStorageError;
+
+impl fmt::Display for SimpleStorageError {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        write!(formatter, "chunk not found")
+    }
+}
+
+impl StorageError for SimpleStorageError {}
+
+// An in-memory `Storage` for tests.  `put_count` tracks how many chunks have been stored, so a
+// test can assert that a resumed encryptor does not re-emit already-flushed chunks.
+pub struct SimpleStorage {
+    map: HashMap<Vec<u8>, Vec<u8>>,
+    pub put_count: usize,
+}
+
+impl SimpleStorage {
+    pub fn new() -> SimpleStorage {
+        SimpleStorage {
+            map: HashMap::new(),
+            put_count: 0,
+        }
+    }
+
+    // Overwrites the chunk stored under `name`, used by tampering tests.
+    pub fn corrupt(&mut self, name: &[u8], data: Vec<u8>) {
+        let _ = self.map.insert(name.to_vec(), data);
+    }
+}
+
+impl Storage for SimpleStorage {
+    type Error = SimpleStorageError;
+
+    fn get(&self, name: &[u8]) -> BoxFuture<Vec<u8>, SimpleStorageError> {
+        match self.map.get(name) {
+            Some(data) => future::ok(data.clone()).into_box(),
+            None => future::err(SimpleStorageError).into_box(),
+        }
+    }
+
+    fn put(&mut self, name: Vec<u8>, data: Vec<u8>) -> BoxFuture<(), SimpleStorageError> {
+        self.put_count += 1;
+        let _ = self.map.insert(name, data);
+        future::ok(()).into_box()
+    }
+}
+
+// Wraps a byte slice so failed comparisons print a short, readable summary rather than a full hex
+// dump of two large buffers.
+pub struct Blob<'a>(pub &'a [u8]);
+
+impl<'a> PartialEq for Blob<'a> {
+    fn eq(&self, other: &Blob) -> bool {
+        self.0 == other.0
+    }
+}
+
+impl<'a> fmt::Debug for Blob<'a> {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        write!(formatter, "Blob(len: {})", self.0.len())
+    }
+}
@@ -0,0 +1,22 @@
+// Copyright 2018 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under The General Public License (GPL), version 3.
+// Unless required by applicable law or agreed to in writing, the SAFE Network Software distributed
+// under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied. Please review the Licences for the specific language governing
+// permissions and limitations relating to use of the SAFE Network Software.
+
+use futures::Future;
+
+// A boxed future, used throughout the crate so the encryptors can return heterogeneous future
+// types from a single method signature.
+pub type BoxFuture<T, E> = Box<Future<Item = T, Error = E>>;
+
+// Convenience for boxing a future into a `BoxFuture`.
+pub trait FutureExt: Future + Sized + 'static {
+    fn into_box(self) -> BoxFuture<Self::Item, Self::Error> {
+        Box::new(self)
+    }
+}
+
+impl<F: Future + Sized + 'static> FutureExt for F {}
@@ -0,0 +1,27 @@
+// Copyright 2018 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under The General Public License (GPL), version 3.
+// Unless required by applicable law or agreed to in writing, the SAFE Network Software distributed
+// under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied. Please review the Licences for the specific language governing
+// permissions and limitations relating to use of the SAFE Network Software.
+
+use rand::Rng;
+
+// Splits `data` into a random number of contiguous pieces, each at least `min_len` bytes (except
+// possibly the last), for driving the encryptors with many small writes.
+pub fn make_random_pieces<'a, T: Rng>(rng: &mut T, data: &'a [u8], min_len: usize) -> Vec<&'a [u8]> {
+    let mut pieces = vec![];
+    let mut offset = 0;
+    while offset < data.len() {
+        let remaining = data.len() - offset;
+        let max = remaining.max(min_len);
+        let len = rng.gen_range(min_len, max + 1).min(remaining).max(1);
+        pieces.push(&data[offset..offset + len]);
+        offset += len;
+    }
+    if pieces.is_empty() {
+        pieces.push(&data[..0]);
+    }
+    pieces
+}